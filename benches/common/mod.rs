@@ -0,0 +1,36 @@
+//! Generators for benchmark input
+
+use std::fmt::Write;
+
+/// Deterministic pseudo random numbers
+pub struct Lcg(pub u32);
+
+impl Lcg {
+    pub fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        self.0 >> 16
+    }
+}
+
+/// Random sequence of A, C, G and T
+pub fn sequence(rng: &mut Lcg, len: usize) -> String {
+    (0..len)
+        .map(|_| ['A', 'C', 'G', 'T'][rng.next() as usize % 4])
+        .collect()
+}
+
+/// TRANSFAC records of count matrices with 8 to 20 positions, each favouring one base
+pub fn matrices(rng: &mut Lcg, n: usize) -> String {
+    let mut c = String::new();
+    for m in 0..n {
+        writeln!(c, "ID M{m}\nXX\nP0 A C G T").unwrap();
+        for i in 0..8 + rng.next() as usize % 13 {
+            let mut row = [0; 4].map(|_| rng.next() % 5);
+            row[rng.next() as usize % 4] += 20;
+            let [a, c_, g, t] = row;
+            writeln!(c, "{:02} {a} {c_} {g} {t}", i + 1).unwrap();
+        }
+        writeln!(c, "XX\n//").unwrap();
+    }
+    c
+}
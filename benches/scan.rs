@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ordered_float::NotNan;
+use tfp::builder::{AmbiguityPolicy, TfpCalculator};
+use tfp::matrix::{ExtendedTfpMatrix, TfpMatrix};
+use tfp::sequence::{Base, PackedSequence, PlusStrand};
+
+mod common;
+
+use common::{matrices, sequence, Lcg};
+
+const SEQUENCE_LENGTH: usize = 2_000_000;
+const MATRICES: usize = 200;
+
+fn calculator() -> TfpCalculator {
+    let mut rng = Lcg(42);
+    let mut c = TfpCalculator::default();
+    c.add_from_transfac_reader(matrices(&mut rng, MATRICES).as_bytes())
+        .unwrap();
+    let seq = sequence(&mut rng, SEQUENCE_LENGTH);
+    c.add_sequence(PlusStrand::from_str("chr", &seq).unwrap());
+    c.set_default_css_threshold(NotNan::new(0.9).unwrap());
+    c.set_default_mss_threshold(NotNan::new(0.85).unwrap());
+    c
+}
+
+/// Looking up the core of every window, by hashing base tuples as before and with the
+/// rolling index into a dense table
+fn core_lookup(criterion: &mut Criterion) {
+    let seq = PlusStrand::from_str("chr", &sequence(&mut Lcg(7), SEQUENCE_LENGTH)).unwrap();
+    let bases: Vec<Base> = seq.seq.iter().map(|n| n.as_base().unwrap()).collect();
+    let table: Vec<f32> = (0..1024).map(|i| i as f32 / 1024.0).collect();
+    let map: HashMap<(Base, Base, Base, Base, Base), f32> = (0..1024usize)
+        .map(|i| {
+            let b = |j: usize| Base::from_code((i >> (2 * (4 - j))) as u64);
+            ((b(0), b(1), b(2), b(3), b(4)), table[i])
+        })
+        .collect();
+
+    let mut group = criterion.benchmark_group("core lookup");
+    group.throughput(Throughput::Elements(SEQUENCE_LENGTH as u64));
+    group.bench_function("hashed tuples", |b| {
+        b.iter(|| {
+            bases
+                .windows(5)
+                .map(|w| map[&(w[0], w[1], w[2], w[3], w[4])])
+                .sum::<f32>()
+        })
+    });
+    group.bench_function("packed table", |b| {
+        b.iter(|| {
+            let packed = PackedSequence::from(seq.seq.as_slice());
+            packed
+                .kmers(5)
+                .map(|i| i.map_or(0.0, |i| table[i]))
+                .sum::<f32>()
+        })
+    });
+    group.finish();
+}
+
+/// Scoring the MSS of every window one at a time and with the block kernel
+fn mss(criterion: &mut Criterion) {
+    let mut rng = Lcg(3);
+    let mut c = TfpCalculator::default();
+    c.add_from_transfac_reader(matrices(&mut rng, 1).as_bytes())
+        .unwrap();
+    let tfp = ExtendedTfpMatrix::try_from(TfpMatrix::from(&c.matrices[0])).unwrap();
+    let seq = PlusStrand::from_str("chr", &sequence(&mut rng, SEQUENCE_LENGTH)).unwrap();
+    let policy = AmbiguityPolicy::Skip;
+
+    let mut group = criterion.benchmark_group("mss");
+    group.throughput(Throughput::Elements(SEQUENCE_LENGTH as u64));
+    group.bench_function("per window", |b| {
+        b.iter(|| {
+            seq.seq
+                .windows(tfp.width())
+                .filter_map(|w| tfp.mss(w, policy))
+                .count()
+        })
+    });
+    group.bench_function("block kernel", |b| {
+        b.iter(|| {
+            tfp.mss_scores(&seq.seq, policy)
+                .into_iter()
+                .flatten()
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn scan(criterion: &mut Criterion) {
+    let calculator = calculator();
+    let scanner = calculator.compile().unwrap();
+    let mut group = criterion.benchmark_group("scan");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(SEQUENCE_LENGTH as u64));
+    group.bench_function(format!("{MATRICES} matrices"), |b| {
+        b.iter(|| scanner.scan_many(&calculator.sequences))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, core_lookup, mss, scan);
+criterion_main!(benches);
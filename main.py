@@ -3,6 +3,9 @@ from tfp import TfpCalculator, parse_profile, parse_transfac, parse_fasta
 c = TfpCalculator()
 c.default_css_threshold = 0.00
 c.default_mss_threshold = 0.00
+c.ambiguity_policy = "skip"
+c.mask_policy = "count"
+c.lenient = False
 c.add_from_transfac_file("test_files/transfac.txt")
 c.add_from_fasta_file("test_files/fasta.txt")
 c.add_from_profile_file("test_files/profile.txt")
@@ -17,12 +20,23 @@ for r in res:
     # css = r.css
     # mss = r.mss
     # len = r.len
+    # site = r.site
+    # masked = r.masked
     print(r)
 
+# Compile once and scan further sequences without rebuilding the matrices
+scanner = c.compile()
+print(scanner)
+
 with open("test_files/fasta.txt") as file:
     fasta = parse_fasta(file.read())
+    for r in scanner.scan_many(fasta):
+        print(r)
     for f in fasta:
         print(f)
+
+# Hits of large inputs can be written to disk while scanning instead of held in memory
+c.write_fasta_file("test_files/fasta.txt", "hits.tsv")
         # print("Fasta Sequence: ", f.name, f.seq)
 with open("test_files/transfac.txt") as file:
     transfac = parse_transfac(file.read())
@@ -0,0 +1,144 @@
+use crate::sequence::{MinusStrand, PlusStrand};
+
+/// Nucleotide model of sequences without binding sites, used for the information vector,
+/// log-odds weights and p-values.
+///
+/// A model of order k gives the probability of a base after the k preceding ones. For the
+/// information vector and log-odds weights, the context of each matrix position is averaged
+/// over the bases the matrix expects before it, see [`Background::expected`]. Words are
+/// indexed in base 4 with A = 0, C = 1, G = 2 and T = 3, the first base being the most
+/// significant digit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    /// Probabilities of all words of length 1 to order + 1, each length summing to one
+    words: Vec<Vec<f64>>,
+    /// Probability of the last base of every word given the bases before it
+    conditional: Vec<Vec<f64>>,
+}
+
+impl Default for Background {
+    /// Uniform 0th-order background
+    fn default() -> Self {
+        Self::from_words(vec![vec![0.25; 4]])
+    }
+}
+
+impl Background {
+    /// 0th-order background from frequencies in A, C, G, T order, which are normalised.
+    /// Returns `None` unless all frequencies are positive and finite.
+    pub fn from_frequencies(frequencies: [f64; 4]) -> Option<Self> {
+        normalise(frequencies.to_vec()).map(|f| Self::from_words(vec![f]))
+    }
+
+    /// Probabilities of all words of length 1 to order + 1, `words[k][w]` being the
+    /// probability of word `w` of length k + 1. Returns `None` if a length has the wrong
+    /// number of words or a probability is not positive.
+    pub fn from_word_probabilities(words: Vec<Vec<f64>>) -> Option<Self> {
+        if words.is_empty()
+            || (1..)
+                .zip(words.iter())
+                .any(|(k, w)| w.len() != 4usize.pow(k))
+        {
+            return None;
+        }
+        let words = words.into_iter().map(normalise).collect::<Option<_>>()?;
+        Some(Self::from_words(words))
+    }
+
+    /// Estimates a model of `order` from both strands of `sequences`, skipping words with
+    /// ambiguous bases. Every word is counted once more, so no probability is zero.
+    pub fn estimate(sequences: &[PlusStrand], order: usize) -> Self {
+        let mut counts: Vec<_> = (1..=order as u32 + 1)
+            .map(|k| vec![1.0; 4usize.pow(k)])
+            .collect();
+        for seq in sequences {
+            let minus = MinusStrand::from(seq);
+            for strand in [&seq.seq, &minus.seq] {
+                for run in strand.split(|n| n.is_ambiguous()) {
+                    let bases: Vec<_> = run.iter().filter_map(|n| n.as_base()).collect();
+                    for (k, counts) in counts.iter_mut().enumerate() {
+                        for w in bases.windows(k + 1) {
+                            counts[w.iter().fold(0, |i, b| i * 4 + *b as usize)] += 1.0;
+                        }
+                    }
+                }
+            }
+        }
+        let words = counts
+            .into_iter()
+            .map(|c| normalise(c).expect("Counts are positive"))
+            .collect();
+        Self::from_words(words)
+    }
+
+    fn from_words(words: Vec<Vec<f64>>) -> Self {
+        let conditional = words
+            .iter()
+            .map(|w| {
+                w.chunks(4)
+                    .flat_map(|c| {
+                        let sum: f64 = c.iter().sum();
+                        c.iter().map(move |p| p / sum)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { words, conditional }
+    }
+
+    /// Number of preceding bases a probability depends on
+    pub fn order(&self) -> usize {
+        self.words.len() - 1
+    }
+
+    /// Probabilities of all words with `len` bases, if the model contains them
+    pub fn words(&self, len: usize) -> Option<&[f64]> {
+        self.words.get(len.checked_sub(1)?).map(Vec::as_slice)
+    }
+
+    /// Base composition in A, C, G, T order
+    pub fn frequencies(&self) -> [f64; 4] {
+        [0, 1, 2, 3].map(|b| self.words[0][b])
+    }
+
+    /// Probability of every base at each position of a site, whose positions have the base
+    /// probabilities `probabilities`. The model's context of a position is weighted by the
+    /// probabilities of the preceding positions, the first positions using shorter contexts.
+    /// A 0th-order model gives its base composition at every position.
+    pub fn expected(&self, probabilities: &[[f64; 4]]) -> Vec<[f64; 4]> {
+        if self.order() == 0 {
+            return vec![self.frequencies(); probabilities.len()];
+        }
+        (0..probabilities.len())
+            .map(|i| {
+                let len = self.order().min(i);
+                let previous = &probabilities[i - len..i];
+                let mut expected = [0.0; 4];
+                for context in 0..4usize.pow(len as u32) {
+                    let weight: f64 = previous
+                        .iter()
+                        .enumerate()
+                        .map(|(j, p)| p[context >> (2 * (len - 1 - j)) & 3])
+                        .product();
+                    for (b, q) in expected.iter_mut().enumerate() {
+                        *q += weight * self.conditional(len, context, b);
+                    }
+                }
+                expected
+            })
+            .collect()
+    }
+
+    /// Probability of `base` after the word `context` of `len` bases, with `len` up to the order
+    pub(crate) fn conditional(&self, len: usize, context: usize, base: usize) -> f64 {
+        self.conditional[len][context * 4 + base]
+    }
+}
+
+fn normalise(values: Vec<f64>) -> Option<Vec<f64>> {
+    if values.iter().any(|v| !v.is_finite() || *v <= 0.0) {
+        return None;
+    }
+    let sum: f64 = values.iter().sum();
+    Some(values.into_iter().map(|v| v / sum).collect())
+}
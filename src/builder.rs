@@ -1,95 +1,321 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 
-use anyhow::anyhow;
+use nom::IResult;
 use rayon::prelude::*;
+use strum::{Display, EnumString};
 use thiserror::Error;
 
-use crate::matrix::{ExtendedTfpMatrix, Float, PwmMatrix, TfpMatrix};
-use crate::parser::fasta::Fasta;
-use crate::parser::profile::{Css, Mss, Profile};
-use crate::parser::transfac::parse_matrices;
-use crate::sequence::{MinusStrand, PlusStrand, Sequence};
+use crate::background::Background;
+use crate::matrix::{
+    ExtendedTfpMatrix, Float, MatrixKind, PwmMatrix, TfpMatrix, DEFAULT_CORE_LENGTH,
+};
+use crate::parser::background::parse_background;
+use crate::parser::bed::read_bed;
+use crate::parser::error::{split_after, split_before, Location, ParseWarning, RecordError};
+use crate::parser::faidx::{IndexedFasta, Region};
+use crate::parser::fasta::{FastaError, FastaReader};
+use crate::parser::homer::parse_homer;
+use crate::parser::input;
+use crate::parser::jaspar::{parse_jaspar, JasparTag};
+use crate::parser::meme::{parse_meme, MemeFile, MemeParseError};
+use crate::parser::profile::{parse_line as parse_profile_line, Css, Mss, Profile, ProfileSet};
+use crate::parser::transfac::{self, parse_matrix, TransfacTag};
+use crate::scanner::Scanner;
+use crate::sequence::PlusStrand;
+use crate::sink::HitSink;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct TfpCalculator {
     pub matrices: Vec<PwmMatrix>,
     pub sequences: Vec<PlusStrand>,
     pub profiles: HashMap<String, (Css, Mss)>,
+    /// All sections of the loaded profile files, see [`TfpCalculator::use_profile_set`]
+    pub profile_sets: Vec<ProfileSet>,
     pub default_css_threshold: Float,
     pub default_mss_threshold: Float,
+    pub ambiguity_policy: AmbiguityPolicy,
+    pub mask_policy: MaskPolicy,
+    /// Derive the MSS threshold of every matrix from this p-value instead
+    pub p_value_threshold: Option<f64>,
+    /// Model of random sequence for the information vector, log-odds and p-values
+    pub background: Background,
+    /// Replace the background by the letter frequencies of MEME motif files when loading them
+    pub meme_background: bool,
+    pub pseudocount_policy: PseudocountPolicy,
+    /// Total pseudocount added per position by the fixed and background policies, 1 by default
+    pub pseudocount: Float,
+    /// Length of the core the CSS is computed on, 5 by default.
+    /// Matrices shorter than the core are scored with the whole matrix as core.
+    pub core_length: usize,
+    /// Number of windows of a strand scanned with one matrix as a unit of parallel work.
+    /// Results do not depend on it.
+    pub chunk_length: usize,
+    /// Skip malformed records while loading files instead of failing
+    pub lenient: bool,
+    /// Problems with records skipped in lenient mode
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl Default for TfpCalculator {
+    fn default() -> Self {
+        Self {
+            matrices: Vec::new(),
+            sequences: Vec::new(),
+            profiles: HashMap::new(),
+            profile_sets: Vec::new(),
+            default_css_threshold: Float::default(),
+            default_mss_threshold: Float::default(),
+            ambiguity_policy: AmbiguityPolicy::default(),
+            mask_policy: MaskPolicy::default(),
+            p_value_threshold: None,
+            background: Background::default(),
+            meme_background: false,
+            pseudocount_policy: PseudocountPolicy::default(),
+            pseudocount: Float::new(1.0).expect("Guaranteed to be a number"),
+            core_length: DEFAULT_CORE_LENGTH,
+            chunk_length: DEFAULT_CHUNK_LENGTH,
+            lenient: false,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Windows per unit of parallel work, see [`TfpCalculator::chunk_length`]
+pub const DEFAULT_CHUNK_LENGTH: usize = 1 << 20;
+
+/// How windows containing ambiguous IUPAC codes (e.g. `N`, `R`, `Y`) are scored
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum AmbiguityPolicy {
+    /// Windows with ambiguous bases are not reported
+    #[default]
+    Skip,
+    /// Score ambiguous positions with the least likely possible base
+    Min,
+    /// Score ambiguous positions with the mean over all possible bases
+    Average,
+    /// Score ambiguous positions with the most likely possible base
+    Max,
+}
+
+/// How matrix rows are converted to probabilities. All policies but `Legacy` take counts as
+/// they are and scale frequencies to the number of sites, assuming 100 if it is unknown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum PseudocountPolicy {
+    /// MATCH conversion of every row to `(100 v + 1) / (100 N + 4)`, for counts and frequencies
+    #[default]
+    Legacy,
+    /// Adds [`TfpCalculator::pseudocount`] in total, evenly over the bases
+    Fixed,
+    /// Adds the square root of the number of sites in total, evenly over the bases
+    SqrtN,
+    /// Adds [`TfpCalculator::pseudocount`] in total, in proportion to the background composition
+    Background,
+}
+
+/// How hits overlapping soft-masked (lowercase) bases are handled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum MaskPolicy {
+    /// Hits overlapping masked bases are not reported
+    Ignore,
+    /// Hits overlapping masked bases are reported and flagged by [`Tfp::masked`]
+    #[default]
+    Count,
+    /// Masked bases are treated like any other base, hits are still flagged by [`Tfp::masked`]
+    Unmask,
 }
 
 #[derive(Debug)]
 pub struct Tfp {
     pub sequence: String,
     pub matrix: String,
+    /// Names of the factors binding to the matrix
+    pub factors: Vec<String>,
+    /// 0-based position of the leftmost site base on the plus strand, for both strands.
+    /// Hits in fetched regions are given in coordinates of the whole reference sequence.
     pub pos: usize,
+    /// Strand of the hit, relative to the reference sequence
     pub strand: bool,
     pub css: Float,
     pub mss: Float,
+    /// Probability of an MSS at least as high in a random window
+    pub p_value: f64,
     pub len: usize,
+    /// Matched bases in matrix orientation, i.e. reverse complemented for minus strand hits
+    pub site: String,
+    /// Whether the site overlaps a soft-masked region
+    pub masked: bool,
 }
 
 #[derive(Debug, Error)]
 pub enum TfpError {
-    #[error("Matrix to short({0}) for Core(5) calculation")]
+    #[error("Matrix to short({0}) for Core calculation")]
     MatrixToShort(usize),
+    #[error("Matrix {0} scores every window the same")]
+    Uninformative(String),
     #[error("File access problem")]
     FileError(std::io::Error),
     #[error("Problem while parsing")]
     ParseError(anyhow::Error),
+    #[error("No known site of matrix {0} could be scored")]
+    NoKnownSites(String),
+    #[error("No profile set named '{0}'")]
+    UnknownProfileSet(String),
+    #[error("Syntax error at {location}, expected {expected}")]
+    Syntax {
+        location: Location,
+        expected: String,
+    },
+    #[error("Invalid record at {location}: {error}")]
+    InvalidRecord {
+        location: Location,
+        error: RecordError,
+    },
+}
+
+impl From<ParseWarning> for TfpError {
+    fn from(w: ParseWarning) -> Self {
+        match w {
+            ParseWarning::Syntax { location, expected } => TfpError::Syntax { location, expected },
+            ParseWarning::InvalidRecord { location, error } => {
+                TfpError::InvalidRecord { location, error }
+            }
+        }
+    }
 }
 
 pub type TfpResult<T> = Result<T, TfpError>;
 
 impl TfpCalculator {
     pub fn add_from_profile_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
-        let c = std::fs::read_to_string(path).map_err(TfpError::FileError)?;
-        let (rest, profiles) = Profile::parse_many(&c)
-            .map_err(|e| TfpError::ParseError(anyhow!("Profile parse error: {e:?}")))?;
-        if !rest.is_empty() {
-            return Err(TfpError::ParseError(anyhow!(
-                "Could not parse completly. {rest}"
-            )));
-        }
-        self.profiles.extend(profiles);
-        Ok(())
+        let c = read_file(&path)?;
+        self.load_profiles(&c, Some(path.as_ref()))
+    }
+
+    pub fn add_from_profile_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_profiles(&read_to_string(reader)?, None)
     }
 
     pub fn add_from_transfac_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
-        let c = std::fs::read_to_string(path).map_err(TfpError::FileError)?;
-        let (rest, mut matrices) = parse_matrices(&c)
-            .map_err(|e| TfpError::ParseError(anyhow!("Transfac parse error: {e:?}")))?;
-        if !rest.is_empty() {
-            return Err(TfpError::ParseError(anyhow!(
-                "Could not parse completly. {rest}"
-            )));
+        let c = read_file(&path)?;
+        self.load_transfac(&c, Some(path.as_ref()))
+    }
+
+    pub fn add_from_transfac_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_transfac(&read_to_string(reader)?, None)
+    }
+
+    pub fn add_from_meme_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let c = read_file(&path)?;
+        self.load_meme(&c, Some(path.as_ref()))
+    }
+
+    pub fn add_from_meme_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_meme(&read_to_string(reader)?, None)
+    }
+
+    /// Adds HOMER motifs, using their header threshold as log-odds cutoff
+    pub fn add_from_homer_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let c = read_file(&path)?;
+        self.load_homer(&c, Some(path.as_ref()))
+    }
+
+    pub fn add_from_homer_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_homer(&read_to_string(reader)?, None)
+    }
+
+    pub fn add_from_jaspar_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let c = read_file(&path)?;
+        self.load_jaspar(&c, Some(path.as_ref()))
+    }
+
+    pub fn add_from_jaspar_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_jaspar(&read_to_string(reader)?, None)
+    }
+
+    /// Adds a single `.pfm` matrix, named after the file if it has no header
+    pub fn add_from_pfm_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let name = path
+            .as_ref()
+            .file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let c = read_file(&path)?;
+        self.load_pfm(&c, Some(path.as_ref()), &name)
+    }
+
+    pub fn add_from_pfm_reader<R: Read>(&mut self, reader: R, name: &str) -> TfpResult<()> {
+        self.load_pfm(&read_to_string(reader)?, None, name)
+    }
+
+    /// Uses the Markov model of a MEME background file, e.g. written by `fasta-get-markov`
+    pub fn set_background_from_meme_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let c = read_file(&path)?;
+        self.load_background(&c, Some(path.as_ref()))
+    }
+
+    pub fn set_background_from_meme_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_background(&read_to_string(reader)?, None)
+    }
+
+    /// Estimates a background of `order` from the added sequences
+    pub fn estimate_background(&mut self, order: usize) {
+        self.background = Background::estimate(&self.sequences, order);
+    }
+
+    pub fn add_from_fasta_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
+        let file = File::open(path.as_ref()).map_err(TfpError::FileError)?;
+        self.load_fasta(file, Some(path.as_ref()))
+    }
+
+    pub fn add_from_fasta_reader<R: Read>(&mut self, reader: R) -> TfpResult<()> {
+        self.load_fasta(reader, None)
+    }
+
+    /// Adds only the given regions of an indexed FASTA file. Hits in these regions are
+    /// reported in coordinates of the whole reference sequence.
+    pub fn add_regions_from_fasta_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        regions: &[Region],
+    ) -> TfpResult<()> {
+        let mut fasta = IndexedFasta::open(path).map_err(TfpError::FileError)?;
+        for region in regions {
+            let seq = fasta.fetch(region).map_err(TfpError::FileError)?;
+            self.sequences.push(seq);
         }
-        let matrices = matrices
-            .drain(..)
-            .map(PwmMatrix::try_from)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| TfpError::ParseError(anyhow!("{e:?}")))?;
-        self.matrices.extend(matrices);
         Ok(())
     }
 
-    pub fn add_from_fasta_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<()> {
-        let c = std::fs::read_to_string(path).map_err(TfpError::FileError)?;
-        let (rest, mut fasta) = Fasta::parse_many(&c)
-            .map_err(|e| TfpError::ParseError(anyhow!("Fasta parse error: {e:?}")))?;
-        if !rest.is_empty() {
-            return Err(TfpError::ParseError(anyhow!(
-                "Could not parse completly. {rest}"
-            )));
+    /// Adds every interval of a BED file, cut out of an indexed FASTA file.
+    /// Sequences are named after the BED name column and minus strand intervals are
+    /// reverse complemented, while hits are still reported in reference coordinates.
+    pub fn add_from_bed_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        bed: P,
+        fasta: Q,
+    ) -> TfpResult<()> {
+        let bed = input::open(bed)
+            .and_then(read_bed)
+            .map_err(TfpError::FileError)?;
+        let mut fasta = IndexedFasta::open(fasta).map_err(TfpError::FileError)?;
+        for record in bed.iter() {
+            let mut seq = fasta
+                .fetch(&Region::from(record))
+                .map_err(TfpError::FileError)?;
+            if let Some(name) = &record.name {
+                seq.name = name.clone();
+            }
+            if record.strand == Some(false) {
+                seq = seq.reverse_complement();
+            }
+            self.sequences.push(seq);
         }
-        let seq = fasta
-            .drain(..)
-            .map(PlusStrand::try_from)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| TfpError::ParseError(anyhow!("{e:?}")))?;
-        self.sequences.extend(seq);
         Ok(())
     }
 
@@ -101,8 +327,23 @@ impl TfpCalculator {
         self.matrices.push(pwm)
     }
 
+    /// Replaces the current profiles by the loaded profile set `name`
+    pub fn use_profile_set(&mut self, name: &str) -> TfpResult<()> {
+        let set = self
+            .profile_sets
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| TfpError::UnknownProfileSet(name.to_string()))?;
+        self.profiles = set
+            .profiles
+            .iter()
+            .map(|p| (p.id.clone(), (p.css, p.mss)))
+            .collect();
+        Ok(())
+    }
+
     pub fn add_profil(&mut self, profile: Profile) {
-        let Profile { css, mss, id } = profile;
+        let Profile { css, mss, id, .. } = profile;
         self.profiles.insert(id, (css, mss));
     }
 
@@ -122,92 +363,437 @@ impl TfpCalculator {
         self.default_mss_threshold
     }
 
-    pub fn evaluate(self) -> Vec<Tfp> {
-        let TfpCalculator {
-            mut matrices,
-            sequences,
-            profiles,
-            default_css_threshold,
-            default_mss_threshold,
-        } = self;
-        let mut plus = sequences;
-        let mut minus: Vec<MinusStrand> = plus.iter().map(MinusStrand::from).collect();
-        let sequences: Vec<_> = plus
-            .drain(..)
-            .map(Sequence::Plus)
-            .chain(minus.drain(..).map(Sequence::Minus))
-            .collect();
-        matrices
-            .par_drain(..)
-            .map(|m| {
-                let (css_threshold, mss_threshold) = profiles
-                    .get(&m.name)
-                    .cloned()
-                    .unwrap_or((default_css_threshold, default_mss_threshold));
-                TfpMatrix {
-                    name: m.name,
-                    matrix: m.matrix,
-                    css_threshold,
-                    mss_threshold,
-                }
-            })
-            .map(ExtendedTfpMatrix::try_from)
-            // This flatten ignores errors from the ExtendedTfpMatrix::try_from function
-            .flatten()
-            .flat_map(|m| {
-                sequences
-                    .iter()
-                    .flat_map(|s| find_significant_bases(&m, s))
-                    .collect::<Vec<_>>()
-            })
-            .collect()
+    pub fn set_ambiguity_policy(&mut self, policy: AmbiguityPolicy) {
+        self.ambiguity_policy = policy;
     }
-}
 
-fn find_significant_bases(tfp: &ExtendedTfpMatrix, seq: &Sequence) -> Vec<Tfp> {
-    let seq_name = seq.name();
-    let ppm_nrows = tfp.ppm.nrows();
-
-    let significant_bases = seq
-        .as_slice()
-        .windows(5)
-        .enumerate()
-        .skip(tfp.core_start)
-        .take(seq.len() - tfp.core_start - (ppm_nrows - tfp.core_start - 5))
-        .map(|(i, s)| (i, tfp.sig_map[&(s[0], s[1], s[2], s[3], s[4])]))
-        .filter(|(_, css)| css >= &tfp.css_threshold);
-
-    significant_bases
-        .map(|(sig_index, css)| {
-            let current: Float = tfp
-                .ppm
-                .row_iter()
-                .zip(tfp.iv.iter())
-                .zip(seq.as_slice().iter().skip(sig_index - tfp.core_start))
-                .map(|((r, iv), b)| iv * r[(0, *b as usize)])
-                .sum();
-            (
-                sig_index,
-                css,
-                (current - tfp.iv_min_sum) / (tfp.iv_max_sum - tfp.iv_min_sum),
-            )
-        })
-        .filter(|(_, _, mss)| mss >= &tfp.mss_threshold)
-        .map(|(i, css, mss)| {
-            let (strand, pos) = match seq {
-                Sequence::Plus(_) => (true, i),
-                Sequence::Minus(_) => (false, seq.len() - i - 5),
+    pub fn get_ambiguity_policy(&self) -> AmbiguityPolicy {
+        self.ambiguity_policy
+    }
+
+    pub fn set_mask_policy(&mut self, policy: MaskPolicy) {
+        self.mask_policy = policy;
+    }
+
+    pub fn get_mask_policy(&self) -> MaskPolicy {
+        self.mask_policy
+    }
+
+    pub fn set_p_value_threshold(&mut self, p_value: Option<f64>) {
+        self.p_value_threshold = p_value;
+    }
+
+    pub fn get_p_value_threshold(&self) -> Option<f64> {
+        self.p_value_threshold
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    pub fn get_background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn set_meme_background(&mut self, meme_background: bool) {
+        self.meme_background = meme_background;
+    }
+
+    pub fn get_meme_background(&self) -> bool {
+        self.meme_background
+    }
+
+    pub fn set_pseudocount_policy(&mut self, policy: PseudocountPolicy) {
+        self.pseudocount_policy = policy;
+    }
+
+    pub fn get_pseudocount_policy(&self) -> PseudocountPolicy {
+        self.pseudocount_policy
+    }
+
+    pub fn set_pseudocount(&mut self, pseudocount: Float) {
+        self.pseudocount = pseudocount;
+    }
+
+    pub fn get_pseudocount(&self) -> Float {
+        self.pseudocount
+    }
+
+    /// Length of the core scored by the CSS. Matrices shorter than the core use their whole
+    /// length, and a length of 0 is treated as 1.
+    pub fn set_core_length(&mut self, core_length: usize) {
+        self.core_length = core_length;
+    }
+
+    pub fn get_core_length(&self) -> usize {
+        self.core_length
+    }
+
+    pub fn set_chunk_length(&mut self, chunk_length: usize) {
+        self.chunk_length = chunk_length;
+    }
+
+    pub fn get_chunk_length(&self) -> usize {
+        self.chunk_length
+    }
+
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    pub fn get_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub fn evaluate(mut self) -> TfpResult<Vec<Tfp>> {
+        let scanner = self.compile()?;
+        Ok(scanner.scan_many(std::mem::take(&mut self.sequences)))
+    }
+
+    /// Like [`Self::evaluate`], but passes hits to `sink` as they are found instead of
+    /// collecting them
+    pub fn evaluate_into<S: HitSink + ?Sized>(mut self, sink: &mut S) -> TfpResult<()> {
+        self.compile()?
+            .scan_into(std::mem::take(&mut self.sequences), sink)
+            .map_err(TfpError::FileError)
+    }
+
+    /// Scans every record of `reader` as soon as it is read, instead of the added sequences.
+    /// Only one record is held in memory at a time.
+    pub fn evaluate_fasta_reader<R: Read>(&mut self, reader: R) -> TfpResult<Vec<Tfp>> {
+        self.evaluate_fasta(reader, None)
+    }
+
+    pub fn evaluate_fasta_file<P: AsRef<Path>>(&mut self, path: P) -> TfpResult<Vec<Tfp>> {
+        let file = File::open(path.as_ref()).map_err(TfpError::FileError)?;
+        self.evaluate_fasta(file, Some(path.as_ref()))
+    }
+
+    /// Like [`Self::evaluate_fasta_reader`], but passes hits to `sink` as they are found,
+    /// so that neither the sequences nor the hits have to fit into memory
+    pub fn evaluate_fasta_reader_into<R: Read, S: HitSink + ?Sized>(
+        &mut self,
+        reader: R,
+        sink: &mut S,
+    ) -> TfpResult<()> {
+        self.evaluate_fasta_into(reader, None, sink)
+    }
+
+    pub fn evaluate_fasta_file_into<P: AsRef<Path>, S: HitSink + ?Sized>(
+        &mut self,
+        path: P,
+        sink: &mut S,
+    ) -> TfpResult<()> {
+        let file = File::open(path.as_ref()).map_err(TfpError::FileError)?;
+        self.evaluate_fasta_into(file, Some(path.as_ref()), sink)
+    }
+
+    /// Fails in strict mode and keeps the problem as warning in lenient mode
+    fn report(&mut self, warning: ParseWarning) -> TfpResult<()> {
+        if !self.lenient {
+            return Err(warning.into());
+        }
+        self.warnings.push(warning);
+        Ok(())
+    }
+
+    fn load_fasta<R: Read>(&mut self, reader: R, path: Option<&Path>) -> TfpResult<()> {
+        let mut sequences = Vec::new();
+        self.for_each_record(reader, path, |seq| {
+            sequences.push(seq);
+            Ok(())
+        })?;
+        self.sequences.extend(sequences);
+        Ok(())
+    }
+
+    fn evaluate_fasta<R: Read>(&mut self, reader: R, path: Option<&Path>) -> TfpResult<Vec<Tfp>> {
+        let scanner = self.compile()?;
+        let mut res = Vec::new();
+        self.for_each_record(reader, path, |seq| {
+            res.extend(scanner.scan(&seq));
+            Ok(())
+        })?;
+        Ok(res)
+    }
+
+    fn evaluate_fasta_into<R: Read, S: HitSink + ?Sized>(
+        &mut self,
+        reader: R,
+        path: Option<&Path>,
+        sink: &mut S,
+    ) -> TfpResult<()> {
+        let scanner = self.compile()?;
+        self.for_each_record(reader, path, |seq| {
+            for hit in scanner.hits([seq]) {
+                sink.write(hit).map_err(TfpError::FileError)?;
+            }
+            Ok(())
+        })?;
+        sink.finish().map_err(TfpError::FileError)
+    }
+
+    /// Calls `f` with every record of the (possibly compressed) FASTA input as it is read.
+    /// Invalid records fail in strict mode and are skipped in lenient mode.
+    fn for_each_record<R, F>(&mut self, reader: R, path: Option<&Path>, mut f: F) -> TfpResult<()>
+    where
+        R: Read,
+        F: FnMut(PlusStrand) -> TfpResult<()>,
+    {
+        let reader = input::decompress(reader).map_err(TfpError::FileError)?;
+        for fasta in FastaReader::new(reader).with_path(path) {
+            match fasta {
+                Ok(fasta) => f(PlusStrand::try_from(fasta).expect("Checked while reading"))?,
+                Err(FastaError::Io(e)) => return Err(TfpError::FileError(e)),
+                Err(FastaError::Invalid(w)) => self.report(w)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn load_profiles(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        let mut tags = Vec::new();
+        for line in c.lines() {
+            match parse_profile_line(line) {
+                Ok((_, tag)) => tags.push(tag),
+                Err(e) => self.report(ParseWarning::from_nom(
+                    c,
+                    e,
+                    path,
+                    "profile entry '<type>,<css>,<mss>,<id>,<name>'",
+                ))?,
+            }
+        }
+        let sets = ProfileSet::from_tags(tags);
+        for p in sets.iter().flat_map(|s| s.profiles.iter()) {
+            self.profiles.insert(p.id.clone(), (p.css, p.mss));
+        }
+        self.profile_sets.extend(sets);
+        Ok(())
+    }
+
+    /// Background files are always loaded strictly, as there is no record to skip
+    fn load_background(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        const EXPECTED: &str = "word and probability";
+        let (rest, tags) = parse_background(c)
+            .map_err(|e| TfpError::from(ParseWarning::from_nom(c, e, path, EXPECTED)))?;
+        let rest = rest.trim_start_matches(['\r', '\n']);
+        if !rest.is_empty() {
+            let location = Location::new(c, rest, path);
+            let expected = EXPECTED.to_string();
+            return Err(TfpError::Syntax { location, expected });
+        }
+        self.background = Background::try_from(tags).map_err(|e| TfpError::InvalidRecord {
+            location: Location::new(c, c, path),
+            error: e.into(),
+        })?;
+        Ok(())
+    }
+
+    fn load_transfac(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        let records = split_after(c, |l| l.starts_with("//"));
+        let parse = |r| {
+            let (rest, mut tags) = parse_matrix(r)?;
+            tags.retain(|t| !matches!(t, TransfacTag::Ignore));
+            let groups = Some(tags).filter(|t| !t.is_empty()).into_iter().collect();
+            Ok((rest, convert(groups)))
+        };
+        self.load_records(c, path, records, parse, transfac::expected)
+    }
+
+    fn load_meme(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        let mut records = split_before(c, |l| l.starts_with("MOTIF"));
+        // Everything before the first motif, like the alphabet and background, applies to all
+        let header = match records.first() {
+            Some(r) if !r.trim_start().starts_with("MOTIF") => records.remove(0),
+            _ => "",
+        };
+        let (_, header_tags) = parse_meme(header)
+            .map_err(|e| TfpError::from(ParseWarning::from_nom(c, e, path, "MEME line")))?;
+        let invalid_header = |e: MemeParseError| ParseWarning::InvalidRecord {
+            location: Location::new(c, header.trim_start(), path),
+            error: e.into(),
+        };
+        let meme = MemeFile::try_from(header_tags.clone()).map_err(invalid_header)?;
+        let background = match (self.meme_background, meme.background) {
+            (true, Some(frequencies)) => Some(
+                Background::from_frequencies(frequencies.map(|f| f64::from(*f)))
+                    .ok_or_else(|| invalid_header(MemeParseError::InvalidBackground))?,
+            ),
+            _ => None,
+        };
+
+        let parse = |r| {
+            let (rest, tags) = parse_meme(r)?;
+            let tags: Vec<_> = header_tags.iter().cloned().chain(tags).collect();
+            let matrices = match MemeFile::try_from(tags) {
+                Ok(meme) => meme.matrices.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e.into())],
             };
+            Ok((rest, matrices))
+        };
+        self.load_records(c, path, records, parse, |_| "MEME line")?;
+        if let Some(background) = background {
+            self.background = background;
+        }
+        Ok(())
+    }
 
-            Tfp {
-                sequence: seq_name.to_string(),
-                matrix: tfp.name.to_string(),
-                pos,
-                strand,
-                css,
-                mss,
-                len: ppm_nrows,
+    fn load_homer(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        let records = split_before(c, |l| l.starts_with('>'));
+        let parse = |r| parse_homer(r).map(|(rest, groups)| (rest, convert(groups)));
+        self.load_records(c, path, records, parse, |_| "HOMER line")
+    }
+
+    fn load_jaspar(&mut self, c: &str, path: Option<&Path>) -> TfpResult<()> {
+        let records = split_before(c, |l| l.starts_with('>'));
+        let parse = |r| parse_jaspar(r).map(|(rest, groups)| (rest, convert(groups)));
+        self.load_records(c, path, records, parse, |_| "JASPAR line")
+    }
+
+    fn load_pfm(&mut self, c: &str, path: Option<&Path>, name: &str) -> TfpResult<()> {
+        let parse = |r| {
+            let (rest, groups) = parse_jaspar(r)?;
+            let mut tags: Vec<_> = groups.into_iter().flatten().collect();
+            if !matches!(tags.first(), Some(JasparTag::Header(..))) {
+                tags.insert(0, JasparTag::Header(name.to_string(), None));
             }
+            // Plain matrices do not declare whether they hold counts or frequencies
+            let matrices = convert(vec![tags])
+                .into_iter()
+                .map(|m| {
+                    m.map(|mut m| {
+                        m.kind = MatrixKind::detect(&m.matrix);
+                        m
+                    })
+                })
+                .collect();
+            Ok((rest, matrices))
+        };
+        self.load_records(c, path, vec![c], parse, |_| "PFM line")
+    }
+
+    /// Parses every record on its own, so problems can be located and skipped in lenient mode.
+    /// In strict mode nothing is added if any record fails.
+    fn load_records<'a, P>(
+        &mut self,
+        c: &'a str,
+        path: Option<&Path>,
+        records: Vec<&'a str>,
+        parse: P,
+        expected: fn(&str) -> &'static str,
+    ) -> TfpResult<()>
+    where
+        P: Fn(&'a str) -> IResult<&'a str, Vec<Result<PwmMatrix, RecordError>>>,
+    {
+        let mut matrices = Vec::new();
+        for record in records {
+            let parsed = match parse(record) {
+                Ok((rest, parsed)) => {
+                    let rest = rest.trim_start_matches(['\r', '\n']);
+                    if rest.is_empty() {
+                        parsed
+                    } else {
+                        let location = Location::new(c, rest, path);
+                        let expected = expected(&location.content).to_string();
+                        self.report(ParseWarning::Syntax { location, expected })?;
+                        continue;
+                    }
+                }
+                Err(e) => {
+                    self.report(ParseWarning::from_nom(c, e, path, expected(record)))?;
+                    continue;
+                }
+            };
+            for pwm in parsed {
+                match pwm {
+                    Ok(pwm) => matrices.push(pwm),
+                    Err(error) => self.report(ParseWarning::InvalidRecord {
+                        location: Location::new(c, record.trim_start(), path),
+                        error,
+                    })?,
+                }
+            }
+        }
+        self.matrices.extend(matrices);
+        Ok(())
+    }
+
+    /// Compiles the matrices with their thresholds and the current settings into a
+    /// [`Scanner`], which can scan any number of sequences.
+    /// Fails if a matrix cannot be scored with these settings, e.g. because it is uniform.
+    pub fn compile(&self) -> TfpResult<Scanner> {
+        Ok(Scanner {
+            matrices: self.compile_matrices()?,
+            ambiguity_policy: self.ambiguity_policy,
+            mask_policy: self.mask_policy,
+            chunk_length: self.chunk_length,
         })
+    }
+
+    fn compile_matrices(&self) -> TfpResult<Vec<ExtendedTfpMatrix>> {
+        self.matrices
+            .par_iter()
+            .map(|m| ExtendedTfpMatrix::try_from(self.tfp_matrix(m)))
+            .collect()
+    }
+
+    /// `m` with its thresholds and the settings it is compiled with
+    pub(crate) fn tfp_matrix(&self, m: &PwmMatrix) -> TfpMatrix {
+        // MATCH profiles refer to matrices by their TRANSFAC accession
+        let (css_threshold, mss_threshold) = self
+            .profiles
+            .get(&m.name)
+            .or_else(|| {
+                m.metadata
+                    .accession
+                    .as_ref()
+                    .and_then(|a| self.profiles.get(a))
+            })
+            .cloned()
+            .unwrap_or((self.default_css_threshold, self.default_mss_threshold));
+        TfpMatrix {
+            name: m.name.clone(),
+            factors: m.metadata.factor_names(),
+            matrix: m.matrix.clone(),
+            css_threshold,
+            mss_threshold,
+            log_odds_threshold: m
+                .metadata
+                .log_odds_threshold
+                .and_then(|t| Float::new(t).ok()),
+            p_value_threshold: self.p_value_threshold,
+            background: self.background.clone(),
+            kind: m.kind,
+            nsites: m.metadata.nsites,
+            pseudocount_policy: self.pseudocount_policy,
+            pseudocount: self.pseudocount,
+            core_length: self.core_length,
+        }
+    }
+}
+
+/// Converts grouped tags to matrices
+fn convert<T, E>(groups: Vec<Vec<T>>) -> Vec<Result<PwmMatrix, RecordError>>
+where
+    PwmMatrix: TryFrom<Vec<T>, Error = E>,
+    RecordError: From<E>,
+{
+    groups
+        .into_iter()
+        .map(|g| PwmMatrix::try_from(g).map_err(RecordError::from))
         .collect()
 }
+
+fn read_file<P: AsRef<Path>>(path: P) -> TfpResult<String> {
+    read_to_string(File::open(path).map_err(TfpError::FileError)?)
+}
+
+/// Reads the whole (possibly compressed) input into memory
+fn read_to_string<R: Read>(reader: R) -> TfpResult<String> {
+    let mut c = String::new();
+    input::decompress(reader)
+        .and_then(|mut r| r.read_to_string(&mut c))
+        .map_err(TfpError::FileError)?;
+    Ok(c)
+}
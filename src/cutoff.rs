@@ -0,0 +1,188 @@
+use std::cmp::Reverse;
+
+use rayon::prelude::*;
+
+use crate::builder::{AmbiguityPolicy, TfpCalculator, TfpError, TfpResult};
+use crate::matrix::{ExtendedTfpMatrix, Float, PwmMatrix};
+use crate::parser::profile::{CutoffType, Profile, ProfileSet};
+use crate::sequence::{MinusStrand, PlusStrand};
+
+/// CSS and MSS of every scorable window on both strands
+fn window_scores(
+    m: &ExtendedTfpMatrix,
+    seq: &PlusStrand,
+    policy: AmbiguityPolicy,
+) -> Vec<(Float, Float)> {
+    let minus = MinusStrand::from(seq);
+    let len = m.ppm.nrows();
+    [&seq.seq, &minus.seq]
+        .into_iter()
+        .flat_map(|s| s.windows(len))
+        .filter_map(|w| Some((m.css(w, policy)?, m.mss(w, policy)?)))
+        .collect()
+}
+
+/// Counts of known sites missed and background windows found for every pair of cutoffs
+struct Errors {
+    css: Vec<Float>,
+    mss: Vec<Float>,
+    /// Indexed by css and mss candidate
+    false_negatives: Vec<Vec<usize>>,
+    false_positives: Vec<Vec<usize>>,
+    sites: usize,
+    windows: usize,
+}
+
+impl Errors {
+    /// Candidate cutoffs are the scores of the known sites
+    fn new(sites: &[(Float, Float)], background: &[(Float, Float)]) -> Self {
+        let mut css: Vec<_> = sites.iter().map(|(c, _)| *c).collect();
+        let mut mss: Vec<_> = sites.iter().map(|(_, m)| *m).collect();
+        css.sort();
+        css.dedup();
+        mss.sort();
+        mss.dedup();
+
+        let false_negatives = passing(&css, &mss, sites)
+            .into_iter()
+            .map(|row| row.into_iter().map(|p| sites.len() - p).collect())
+            .collect();
+        let false_positives = passing(&css, &mss, background);
+
+        Self {
+            css,
+            mss,
+            false_negatives,
+            false_positives,
+            sites: sites.len(),
+            windows: background.len(),
+        }
+    }
+
+    /// Cutoffs minimising `key`, preferring lower cutoffs on ties
+    fn best<K: Ord, F: Fn(usize, usize) -> K>(&self, key: F) -> (Float, Float) {
+        let (i, j) = (0..self.css.len())
+            .flat_map(|i| (0..self.mss.len()).map(move |j| (i, j)))
+            .min_by_key(|(i, j)| key(*i, *j))
+            .expect("Guaranteed to have at least one site");
+        (self.css[i], self.mss[j])
+    }
+
+    fn cutoff(&self, cutoff: CutoffType) -> (Float, Float) {
+        let fnr = |i: usize, j: usize| self.false_negatives[i][j] as f64 / self.sites as f64;
+        let fpr =
+            |i: usize, j: usize| self.false_positives[i][j] as f64 / self.windows.max(1) as f64;
+        match cutoff {
+            CutoffType::MinFn => {
+                self.best(|i, j| (self.false_negatives[i][j], self.false_positives[i][j]))
+            }
+            CutoffType::MinFp => {
+                self.best(|i, j| (self.false_positives[i][j], self.false_negatives[i][j]))
+            }
+            CutoffType::MinSum => self.best(|i, j| {
+                let sum = Float::new((fnr(i, j) + fpr(i, j)) as f32).expect("Rates are finite");
+                (sum, self.false_negatives[i][j])
+            }),
+        }
+    }
+}
+
+/// Number of windows passing every pair of sorted css and mss candidates, indexed like them
+fn passing(css: &[Float], mss: &[Float], windows: &[(Float, Float)]) -> Vec<Vec<usize>> {
+    // Sweep the css candidates downwards, adding the windows passing them to a histogram
+    // over the number of mss candidates they pass
+    let mut windows = windows.to_vec();
+    windows.sort_by_key(|w| Reverse(w.0));
+    let mut histogram = vec![0; mss.len() + 1];
+    let mut added = 0;
+    let mut passing = vec![Vec::new(); css.len()];
+    for (i, c) in css.iter().enumerate().rev() {
+        while added < windows.len() && windows[added].0 >= *c {
+            histogram[mss.partition_point(|m| *m <= windows[added].1)] += 1;
+            added += 1;
+        }
+        // Windows passing mss candidate j pass more than j candidates
+        let mut count = 0;
+        let mut row = vec![0; mss.len()];
+        for j in (0..mss.len()).rev() {
+            count += histogram[j + 1];
+            row[j] = count;
+        }
+        passing[i] = row;
+    }
+    passing
+}
+
+/// Computes minFN, minFP and minSUM cutoffs of a matrix, in this order.
+///
+/// Each known site is scored by its best window on either strand, the background by all
+/// windows. minFN keeps every known site and minFP finds as few background windows as
+/// possible, each breaking ties by the other error. minSUM minimises the sum of both rates.
+/// The matrix is compiled and scored with the settings of `calculator`, like when scanning.
+pub fn generate_profiles(
+    pwm: &PwmMatrix,
+    sites: &[PlusStrand],
+    background: &[PlusStrand],
+    calculator: &TfpCalculator,
+) -> TfpResult<[Profile; 3]> {
+    let m = ExtendedTfpMatrix::try_from(calculator.tfp_matrix(pwm))?;
+    let policy = calculator.ambiguity_policy;
+
+    let known: Vec<_> = sites
+        .par_iter()
+        .filter_map(|s| {
+            window_scores(&m, s, policy)
+                .into_iter()
+                .max_by_key(|(css, mss)| (*mss, *css))
+        })
+        .collect();
+    if known.is_empty() {
+        return Err(TfpError::NoKnownSites(pwm.name.clone()));
+    }
+    let background: Vec<_> = background
+        .par_iter()
+        .flat_map_iter(|s| window_scores(&m, s, policy))
+        .collect();
+    let errors = Errors::new(&known, &background);
+
+    // Profiles refer to TRANSFAC matrices by accession
+    let (id, factor) = match &pwm.metadata.accession {
+        Some(accession) => (accession.clone(), Some(pwm.name.clone())),
+        None => (pwm.name.clone(), pwm.metadata.factor.clone()),
+    };
+    Ok(
+        [CutoffType::MinFn, CutoffType::MinFp, CutoffType::MinSum].map(|cutoff| {
+            let (css, mss) = errors.cutoff(cutoff);
+            Profile {
+                cutoff,
+                css,
+                mss,
+                id: id.clone(),
+                factor: factor.clone(),
+            }
+        }),
+    )
+}
+
+/// Generates the sets `minFN`, `minFP` and `minSUM` for matrices with their known sites,
+/// ready to be written by [`crate::parser::profile::write_profiles`]
+pub fn generate_profile_sets<'a, I>(
+    matrices: I,
+    background: &[PlusStrand],
+    calculator: &TfpCalculator,
+) -> TfpResult<Vec<ProfileSet>>
+where
+    I: IntoIterator<Item = (&'a PwmMatrix, &'a [PlusStrand])>,
+{
+    let mut sets = ["minFN", "minFP", "minSUM"].map(|name| ProfileSet {
+        name: name.to_string(),
+        ..Default::default()
+    });
+    for (pwm, sites) in matrices {
+        let profiles = generate_profiles(pwm, sites, background, calculator)?;
+        for (set, profile) in sets.iter_mut().zip(profiles) {
+            set.profiles.push(profile);
+        }
+    }
+    Ok(sets.into())
+}
@@ -1,7 +1,11 @@
+pub mod background;
 pub mod builder;
+pub mod cutoff;
 pub mod matrix;
 pub mod parser;
+pub mod scanner;
 pub mod sequence;
+pub mod sink;
 
 // transcription factor prediction
 // https://academic.oup.com/nar/article/31/13/3576/2904207
@@ -1,47 +1,389 @@
-use std::collections::HashMap;
-
-use itertools::Itertools;
 use nalgebra::{Const, Dynamic, Matrix, Matrix1x4, VecStorage, Vector};
 use ordered_float::NotNan;
-use strum::IntoEnumIterator;
+use strum::{Display, EnumString};
 
-use crate::builder::TfpError;
-use crate::sequence::Base;
+use crate::background::Background;
+use crate::builder::{AmbiguityPolicy, PseudocountPolicy, TfpError};
+use crate::sequence::{Base, Nucleotide, PackedSequence};
 
 pub type Float = NotNan<f32>;
 pub type PwmMatrixInner = Matrix<Float, Dynamic, Const<4>, VecStorage<Float, Dynamic, Const<4>>>;
 pub type PpmMatrix = PwmMatrixInner;
 pub type IvVector = Vector<Float, Dynamic, VecStorage<Float, Dynamic, Const<1>>>;
 pub type MaxVector = IvVector;
-pub type SigMap = HashMap<(Base, Base, Base, Base, Base), Float>;
 
-#[derive(Debug, Clone)]
+/// Core length of the MATCH algorithm
+pub const DEFAULT_CORE_LENGTH: usize = 5;
+/// Longest core for which all CSS are precomputed, taking 4^7 values
+const MAX_CSS_TABLE_CORE: usize = 7;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PwmMatrix {
     pub name: String,
     pub matrix: PwmMatrixInner,
+    pub kind: MatrixKind,
+    pub metadata: MatrixMetadata,
+}
+
+/// What the values of a matrix are
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum MatrixKind {
+    /// Number of sites with each base
+    #[default]
+    Counts,
+    /// Probabilities, every row sums to one
+    Frequencies,
+}
+
+impl MatrixKind {
+    /// Frequencies if every row sums to one, allowing for rounding in the source file.
+    /// Only a guess for input that does not declare its kind, as counts of a single site
+    /// look like frequencies.
+    pub fn detect(matrix: &PwmMatrixInner) -> Self {
+        let frequencies =
+            matrix.nrows() > 0 && matrix.row_iter().all(|r| (*r.sum() - 1.0).abs() <= 0.02);
+        match frequencies {
+            true => MatrixKind::Frequencies,
+            false => MatrixKind::Counts,
+        }
+    }
+}
+
+/// Optional information about a matrix, as far as the source format provides it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatrixMetadata {
+    /// Name of the transcription factor, e.g. the MEME alternate name
+    pub factor: Option<String>,
+    /// Number of sites the matrix was built from
+    pub nsites: Option<f32>,
+    pub e_value: Option<f64>,
+    pub url: Option<String>,
+    pub accession: Option<String>,
+    pub description: Option<String>,
+    pub binding_factors: Vec<BindingFactor>,
+    /// Sites or publications the matrix is based on
+    pub basis: Option<String>,
+    pub comment: Option<String>,
+    /// Minimal log-odds score of a hit, e.g. the detection threshold of a HOMER motif
+    pub log_odds_threshold: Option<f32>,
+}
+
+impl MatrixMetadata {
+    /// Names of all binding factors, or the factor name if there are none
+    pub fn factor_names(&self) -> Vec<String> {
+        match self.binding_factors.is_empty() {
+            true => self.factor.iter().cloned().collect(),
+            false => self
+                .binding_factors
+                .iter()
+                .map(|f| f.name.clone())
+                .collect(),
+        }
+    }
+
+    /// Case insensitive match against the factor name and all binding factors
+    pub fn has_factor(&self, name: &str) -> bool {
+        self.factor
+            .iter()
+            .chain(self.binding_factors.iter().map(|f| &f.name))
+            .any(|f| f.eq_ignore_ascii_case(name))
+    }
+
+    /// Case insensitive search for `species` in the species of all binding factors,
+    /// e.g. `mouse` or `Mus musculus`
+    pub fn has_species(&self, species: &str) -> bool {
+        let species = species.to_lowercase();
+        self.binding_factors
+            .iter()
+            .filter_map(|f| f.species.as_ref())
+            .any(|s| s.to_lowercase().contains(&species))
+    }
+}
+
+/// A factor binding to a matrix, given by the TRANSFAC `BF` field
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindingFactor {
+    pub id: String,
+    pub name: String,
+    pub species: Option<String>,
 }
 
 #[derive(Debug)]
 pub struct TfpMatrix {
     pub name: String,
+    pub factors: Vec<String>,
     pub matrix: PwmMatrixInner,
     pub css_threshold: Float,
     pub mss_threshold: Float,
+    pub log_odds_threshold: Option<Float>,
+    /// Replaces the MSS threshold by the one reaching this p-value
+    pub p_value_threshold: Option<f64>,
+    pub background: Background,
+    pub kind: MatrixKind,
+    /// Number of sites behind a frequency matrix
+    pub nsites: Option<f32>,
+    pub pseudocount_policy: PseudocountPolicy,
+    pub pseudocount: Float,
+    /// Shortened to the matrix length for short matrices
+    pub core_length: usize,
+}
+
+/// Scores a matrix like MATCH without cutoffs: no thresholds, uniform background,
+/// legacy pseudocounts and a core of 5
+impl From<&PwmMatrix> for TfpMatrix {
+    fn from(pwm: &PwmMatrix) -> Self {
+        Self {
+            name: pwm.name.clone(),
+            factors: pwm.metadata.factor_names(),
+            matrix: pwm.matrix.clone(),
+            css_threshold: Float::default(),
+            mss_threshold: Float::default(),
+            log_odds_threshold: None,
+            p_value_threshold: None,
+            background: Background::default(),
+            kind: pwm.kind,
+            nsites: pwm.metadata.nsites,
+            pseudocount_policy: PseudocountPolicy::Legacy,
+            pseudocount: Float::default(),
+            core_length: DEFAULT_CORE_LENGTH,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ExtendedTfpMatrix {
     pub(crate) name: String,
+    pub(crate) factors: Vec<String>,
     pub(crate) css_threshold: Float,
     pub(crate) mss_threshold: Float,
     pub(crate) ppm: PpmMatrix,
     pub(crate) iv: IvVector,
     pub(crate) iv_max_sum: Float,
     pub(crate) iv_min_sum: Float,
+    /// `iv * ppm` of every position and base, the MSS contributions as plain numbers
+    pub(crate) weights: Vec<[f32; 4]>,
     pub(crate) core_start: usize,
-    // pub(crate) core_max: Float,
-    // pub(crate) core_min: Float,
-    pub(crate) sig_map: SigMap,
+    pub(crate) core_length: usize,
+    pub(crate) core_max: Float,
+    pub(crate) core_min: Float,
+    /// CSS of every core indexed like [`crate::sequence::PackedSequence::kmers`],
+    /// empty for cores longer than 7 bases
+    pub(crate) css_table: Vec<Float>,
+    /// Log-odds weights and the minimal score, if the matrix has a log-odds cutoff
+    pub(crate) log_odds: Option<(PwmMatrixInner, Float)>,
+    /// Per position MSS contributions in steps of `1 / P_VALUE_STEPS`
+    pub(crate) discrete_weights: Vec<[u32; 4]>,
+    /// Probability of a discrete score of at least the index under the background
+    pub(crate) p_values: Vec<f64>,
+    pub(crate) p_value_threshold: Option<f64>,
+}
+
+/// Resolution of the MSS range used for p-values
+const P_VALUE_STEPS: f64 = 10_000.0;
+
+/// Number of windows scored together by [`ExtendedTfpMatrix::raw_mss`]
+const MSS_BLOCK: usize = 64;
+
+impl ExtendedTfpMatrix {
+    /// Number of positions, the length of scored windows
+    pub fn width(&self) -> usize {
+        self.ppm.nrows()
+    }
+
+    /// Core similarity score of a window with the length of the matrix.
+    /// Returns `None` if the window can not be scored with the given policy.
+    pub(crate) fn css(&self, window: &[Nucleotide], policy: AmbiguityPolicy) -> Option<Float> {
+        let core = &window[self.core_start..self.core_start + self.core_length];
+        let index = core
+            .iter()
+            .try_fold(0, |i, n| Some(i * 4 + n.as_base()? as usize));
+        self.css_of_core(index, window, policy)
+    }
+
+    /// CSS of a window whose core has the word index `core`, if known.
+    /// Falls back to scoring the window for ambiguous cores or cores without table.
+    pub(crate) fn css_of_core(
+        &self,
+        core: Option<usize>,
+        window: &[Nucleotide],
+        policy: AmbiguityPolicy,
+    ) -> Option<Float> {
+        if let Some(css) = core.and_then(|i| self.css_table.get(i)) {
+            return Some(*css);
+        }
+        let core = &window[self.core_start..self.core_start + self.core_length];
+        let current = core
+            .iter()
+            .enumerate()
+            .map(|(i, n)| self.weight(self.core_start + i, *n, policy))
+            .sum::<Option<Float>>()?;
+        Some((current - self.core_min) / (self.core_max - self.core_min))
+    }
+
+    /// Matrix similarity score of a window with the length of the matrix.
+    /// Returns `None` if the window can not be scored with the given policy.
+    pub fn mss(&self, window: &[Nucleotide], policy: AmbiguityPolicy) -> Option<Float> {
+        let current = window
+            .iter()
+            .enumerate()
+            .map(|(i, n)| self.weight(i, *n, policy))
+            .sum::<Option<Float>>()?;
+        Some((current - self.iv_min_sum) / (self.iv_max_sum - self.iv_min_sum))
+    }
+
+    /// MSS of every window of `seq`, computed block-wise like during scanning.
+    /// Gives the same results as [`Self::mss`] on each window.
+    pub fn mss_scores(&self, seq: &[Nucleotide], policy: AmbiguityPolicy) -> Vec<Option<Float>> {
+        let packed = PackedSequence::from(seq);
+        let mut scorer = MssScorer::new(self, &packed);
+        seq.windows(self.width())
+            .enumerate()
+            .map(|(i, w)| scorer.mss(i, w, policy))
+            .collect()
+    }
+
+    /// Unnormalised MSS of consecutive windows, with `codes` holding the 2-bit codes of all
+    /// their bases. Every window is summed position by position like in [`Self::mss`], so the
+    /// results are identical, while the inner loop over the windows can be vectorised.
+    fn raw_mss(&self, codes: &[u8], scores: &mut [f32]) {
+        // Starts like the sum of floats, keeping the sign of zero scores
+        scores.fill(-0.0);
+        for (i, w) in self.weights.iter().enumerate() {
+            for (s, c) in scores.iter_mut().zip(&codes[i..]) {
+                *s += w[(c & 0b11) as usize];
+            }
+        }
+    }
+
+    /// Probability of a window scoring at least as high under the background.
+    /// Exact for the MSS discretised to `1 / 10000` per position.
+    pub(crate) fn p_value(&self, window: &[Nucleotide], policy: AmbiguityPolicy) -> Option<f64> {
+        let score = window
+            .iter()
+            .zip(self.discrete_weights.iter())
+            .map(|(n, w)| {
+                let weight = ambiguous_weight(*n, policy, |b| {
+                    Float::new(w[b as usize] as f32).expect("Guaranteed to be a number")
+                })?;
+                Some(weight.round() as usize)
+            })
+            .sum::<Option<usize>>()?;
+        Some(self.p_values[score.min(self.p_values.len() - 1)])
+    }
+
+    /// Whether a window passes the p-value cutoff, always true without one
+    pub(crate) fn passes_p_value(&self, p_value: f64) -> bool {
+        self.p_value_threshold.is_none_or(|t| p_value <= t)
+    }
+
+    /// Whether a window reaches the log-odds cutoff, always true for matrices without one
+    pub(crate) fn passes_log_odds(&self, window: &[Nucleotide], policy: AmbiguityPolicy) -> bool {
+        let (weights, threshold) = match &self.log_odds {
+            Some(l) => l,
+            None => return true,
+        };
+        window
+            .iter()
+            .enumerate()
+            .map(|(i, n)| ambiguous_weight(*n, policy, |b| weights[(i, b as usize)]))
+            .sum::<Option<Float>>()
+            .is_some_and(|score| score >= *threshold)
+    }
+
+    fn weight(&self, row: usize, n: Nucleotide, policy: AmbiguityPolicy) -> Option<Float> {
+        let iv = self.iv[row];
+        ambiguous_weight(n, policy, |b| iv * self.ppm[(row, b as usize)])
+    }
+}
+
+/// MSS of the windows of one strand, scoring unambiguous windows block-wise
+/// with [`ExtendedTfpMatrix::raw_mss`]
+pub(crate) struct MssScorer<'a> {
+    tfp: &'a ExtendedTfpMatrix,
+    packed: &'a PackedSequence,
+    codes: Vec<u8>,
+    raw: [f32; MSS_BLOCK],
+    /// First window of the scored block
+    block: Option<usize>,
+}
+
+impl<'a> MssScorer<'a> {
+    pub(crate) fn new(tfp: &'a ExtendedTfpMatrix, packed: &'a PackedSequence) -> Self {
+        Self {
+            tfp,
+            packed,
+            codes: Vec::new(),
+            raw: [0.0; MSS_BLOCK],
+            block: None,
+        }
+    }
+
+    /// MSS of `window`, which starts at position `i` of the packed sequence
+    pub(crate) fn mss(
+        &mut self,
+        i: usize,
+        window: &[Nucleotide],
+        policy: AmbiguityPolicy,
+    ) -> Option<Float> {
+        if window.iter().any(|n| n.is_ambiguous()) {
+            return self.tfp.mss(window, policy);
+        }
+        let len = window.len();
+        let start = i - i % MSS_BLOCK;
+        if self.block != Some(start) {
+            let windows = MSS_BLOCK.min(self.packed.len() + 1 - len - start);
+            self.packed
+                .unpack(start..start + windows + len - 1, &mut self.codes);
+            self.tfp.raw_mss(&self.codes, &mut self.raw[..windows]);
+            self.block = Some(start);
+        }
+        let (min, max) = (*self.tfp.iv_min_sum, *self.tfp.iv_max_sum);
+        Float::new((self.raw[i - start] - min) / (max - min)).ok()
+    }
+}
+
+/// Weight of a nucleotide, combining the weights of all possible bases if it is ambiguous
+fn ambiguous_weight<F: Fn(Base) -> Float>(
+    n: Nucleotide,
+    policy: AmbiguityPolicy,
+    weight: F,
+) -> Option<Float> {
+    if let Some(b) = n.as_base() {
+        return Some(weight(b));
+    }
+    let weights = n.bases().iter().map(|b| weight(*b));
+    match policy {
+        AmbiguityPolicy::Skip => None,
+        AmbiguityPolicy::Min => weights.min(),
+        AmbiguityPolicy::Average => Some(weights.sum::<Float>() / n.bases().len() as f32),
+        AmbiguityPolicy::Max => weights.max(),
+    }
+}
+
+/// Log-odds weights `ln(p / q)` of a count or probability matrix against the base
+/// composition `q` of the background.
+/// Probabilities are floored at 0.001 to keep the weights finite.
+pub fn gen_log_odds(pwm: &PwmMatrixInner, background: &Background) -> PwmMatrixInner {
+    const MIN: Float = unsafe { Float::new_unchecked(0.001) };
+    const QUARTER: Float = unsafe { Float::new_unchecked(0.25) };
+    let mut log_odds = pwm.clone();
+    for mut r in log_odds.row_iter_mut() {
+        let sum = r.sum();
+        for v in r.iter_mut() {
+            *v = match *sum > 0.0 {
+                true => (*v / sum).max(MIN),
+                false => QUARTER,
+            };
+        }
+    }
+    let expected = background.expected(&probabilities(&log_odds));
+    for (mut r, q) in log_odds.row_iter_mut().zip(expected) {
+        for (v, q) in r.iter_mut().zip(q) {
+            *v = Float::new((**v / q as f32).ln()).expect("Guaranteed to be finite");
+        }
+    }
+    log_odds
 }
 
 impl TryFrom<TfpMatrix> for ExtendedTfpMatrix {
@@ -50,78 +392,186 @@ impl TryFrom<TfpMatrix> for ExtendedTfpMatrix {
     fn try_from(pwm: TfpMatrix) -> Result<Self, Self::Error> {
         let TfpMatrix {
             name,
+            factors,
             mut matrix,
             css_threshold,
             mss_threshold,
+            log_odds_threshold,
+            p_value_threshold,
+            background,
+            kind,
+            nsites,
+            pseudocount_policy,
+            pseudocount,
+            core_length,
         } = pwm;
-        to_ppm(&mut matrix);
+        let log_odds = log_odds_threshold.map(|t| (gen_log_odds(&matrix, &background), t));
+        match pseudocount_policy {
+            PseudocountPolicy::Legacy => to_ppm(&mut matrix),
+            policy => {
+                let sites = match kind {
+                    MatrixKind::Counts => None,
+                    MatrixKind::Frequencies => Some(nsites.unwrap_or(100.0)),
+                };
+                add_pseudocounts(&mut matrix, sites, policy, *pseudocount, &background)
+            }
+        }
         let ppm = matrix;
-        let iv = gen_iv(&ppm);
+        let iv = gen_iv(&ppm, &background);
         let iv_max_sum = iv_max_sum(&iv, &ppm);
         let iv_min_sum = iv_min_sum(&iv, &ppm);
+        let weights = ppm
+            .row_iter()
+            .zip(iv.iter())
+            .map(|(r, iv)| [0, 1, 2, 3].map(|b| *(*iv * r[b])))
+            .collect();
         let max_vector = gen_max_vector(&ppm, &iv);
-        // TODO return real error
-        let (core_start, core_max) =
-            find_core(&max_vector).ok_or_else(|| TfpError::MatrixToShort(ppm.nrows()))?;
-        let core_min = min_from_core(core_start, &ppm, &iv);
-        let sig_map = gen_sig_map(core_start, core_min, core_max, &ppm, &iv);
+        // Short matrices are scored with the whole matrix as core
+        let core_length = core_length.clamp(1, ppm.nrows().max(1));
+        let (core_start, core_max) = find_core(&max_vector, core_length)
+            .ok_or_else(|| TfpError::MatrixToShort(ppm.nrows()))?;
+        let core_min = min_from_core(core_start, core_length, &ppm, &iv);
+        // Scores are normalised by their range, which uniform matrices do not have
+        if core_max == core_min || iv_max_sum == iv_min_sum {
+            return Err(TfpError::Uninformative(name));
+        }
+        let css_table = match core_length <= MAX_CSS_TABLE_CORE {
+            true => gen_css_table(core_start, core_length, core_min, core_max, &ppm, &iv),
+            false => Vec::new(),
+        };
+        let discrete_weights = gen_discrete_weights(&ppm, &iv, iv_min_sum, iv_max_sum);
+        let p_values = gen_p_values(&discrete_weights, &background);
+        let mss_threshold = match p_value_threshold {
+            Some(p) => mss_for_p_value(&p_values, ppm.nrows(), p),
+            None => mss_threshold,
+        };
 
         Ok(Self {
             name,
+            factors,
             css_threshold,
             mss_threshold,
             ppm,
             iv,
             iv_max_sum,
             iv_min_sum,
+            weights,
             core_start,
-            // core_max,
-            // core_min,
-            sig_map,
+            core_length,
+            core_max,
+            core_min,
+            css_table,
+            log_odds,
+            discrete_weights,
+            p_values,
+            p_value_threshold,
         })
     }
 }
 
-fn gen_sig_map(
+fn gen_discrete_weights(
+    ppm: &PpmMatrix,
+    iv: &IvVector,
+    iv_min_sum: Float,
+    iv_max_sum: Float,
+) -> Vec<[u32; 4]> {
+    let range = f64::from(*(iv_max_sum - iv_min_sum));
+    ppm.row_iter()
+        .zip(iv.iter())
+        .map(|(r, iv)| {
+            let min = *iv * *r.iter().min().expect("Guaranteed to not be 'None'");
+            [0, 1, 2, 3].map(|b| match range > 0.0 {
+                true => (f64::from(*(*iv * r[b] - min)) / range * P_VALUE_STEPS).round() as u32,
+                false => 0,
+            })
+        })
+        .collect()
+}
+
+/// Tail probabilities of the discrete score by dynamic programming over all positions.
+/// For Markov backgrounds the score distribution is kept per context of preceding bases,
+/// so time and memory grow with `4^order`.
+fn gen_p_values(weights: &[[u32; 4]], background: &Background) -> Vec<f64> {
+    let max: usize = weights
+        .iter()
+        .map(|w| w.iter().max().copied().unwrap_or(0) as usize)
+        .sum();
+    let order = background.order();
+    let mut dist = vec![vec![0.0; max + 1]];
+    dist[0][0] = 1.0;
+    let mut reach = 0;
+    for (i, w) in weights.iter().enumerate() {
+        let contexts = 4usize.pow((i + 1).min(order) as u32);
+        let mut next = vec![vec![0.0; max + 1]; contexts];
+        for (context, d) in dist.iter().enumerate() {
+            for (s, p) in d
+                .iter()
+                .enumerate()
+                .take(reach + 1)
+                .filter(|(_, p)| **p > 0.0)
+            {
+                for (b, weight) in w.iter().enumerate() {
+                    let q = background.conditional(i.min(order), context, b);
+                    next[(context * 4 + b) % contexts][s + *weight as usize] += p * q;
+                }
+            }
+        }
+        reach += w.iter().max().copied().unwrap_or(0) as usize;
+        dist = next;
+    }
+    let mut tail = vec![0.0; max + 1];
+    for d in dist {
+        tail.iter_mut().zip(d).for_each(|(t, p)| *t += p);
+    }
+    for s in (0..max).rev() {
+        tail[s] += tail[s + 1];
+    }
+    tail
+}
+
+/// Lowest MSS a window reaching the p-value can have. Rounding the discrete weights moves
+/// scores by up to half a step per position, so the exact p-value has to be checked as well.
+fn mss_for_p_value(p_values: &[f64], len: usize, p_value: f64) -> Float {
+    let score = p_values.partition_point(|p| *p > p_value);
+    let mss = (score as f64 - len as f64 / 2.0) / P_VALUE_STEPS;
+    Float::new(mss.max(0.0) as f32).expect("Guaranteed to be a number")
+}
+
+fn gen_css_table(
     core_index: usize,
+    core_length: usize,
     core_min: Float,
     core_max: Float,
     ppm: &PpmMatrix,
     iv: &IvVector,
-) -> SigMap {
-    let core = ppm.rows(core_index, 5);
-    let core_iv = iv.rows(core_index, 5);
-    Base::iter()
-        .cartesian_product(Base::iter())
-        .cartesian_product(Base::iter())
-        .cartesian_product(Base::iter())
-        .cartesian_product(Base::iter())
-        .map(|((((a, b), c), d), e)| {
-            let current = core[(0, a as usize)] * core_iv[0]
-                + core[(1, b as usize)] * core_iv[1]
-                + core[(2, c as usize)] * core_iv[2]
-                + core[(3, d as usize)] * core_iv[3]
-                + core[(4, e as usize)] * core_iv[4];
-            (
-                (a, b, c, d, e),
-                (current - core_min) / (core_max - core_min),
-            )
+) -> Vec<Float> {
+    let core = ppm.rows(core_index, core_length);
+    let core_iv = iv.rows(core_index, core_length);
+    (0..4usize.pow(core_length as u32))
+        .map(|index| {
+            let current = (0..core_length)
+                .map(|i| {
+                    let b = index >> (2 * (core_length - 1 - i)) & 0b11;
+                    core[(i, b)] * core_iv[i]
+                })
+                .sum::<Float>();
+            (current - core_min) / (core_max - core_min)
         })
         .collect()
 }
 
-fn min_from_core(core_index: usize, ppm: &PpmMatrix, iv: &IvVector) -> Float {
-    ppm.rows(core_index, 5)
+fn min_from_core(core_index: usize, core_length: usize, ppm: &PpmMatrix, iv: &IvVector) -> Float {
+    ppm.rows(core_index, core_length)
         .row_iter()
-        .zip(iv.rows(core_index, 5).iter())
+        .zip(iv.rows(core_index, core_length).iter())
         .map(|(r, iv)| iv * r.iter().min().expect("Guaranteed to not be 'None'"))
         .sum()
 }
 
-fn find_core(max_vec: &MaxVector) -> Option<(usize, Float)> {
+fn find_core(max_vec: &MaxVector, core_length: usize) -> Option<(usize, Float)> {
     max_vec
         .as_slice()
-        .windows(5)
+        .windows(core_length)
         .map(|a| a.iter().sum::<Float>())
         .enumerate()
         .max_by_key(|(_, k)| *k)
@@ -155,16 +605,64 @@ fn iv_max_sum(iv: &IvVector, ppm: &PpmMatrix) -> Float {
         .sum()
 }
 
-fn gen_iv(ppm: &PpmMatrix) -> IvVector {
-    const FOUR: Float = unsafe { Float::new_unchecked(4.0) };
-    // TODO Circumvent clone
-    let mut iv = ppm.clone();
-    iv.iter_mut()
-        .map(|c| *c = *c * (FOUR * *c).ln())
-        .for_each(drop);
-    iv.column_sum()
+fn gen_iv(ppm: &PpmMatrix, background: &Background) -> IvVector {
+    let expected = background.expected(&probabilities(ppm));
+    let iv = ppm.row_iter().zip(expected).map(|(r, q)| {
+        (0..4).fold(Float::default(), |sum, b| {
+            // Multiplying by the inverse keeps the uniform case exact
+            let inverse = Float::new((1.0 / q[b]) as f32).expect("Background is positive");
+            sum + r[b] * (inverse * r[b]).ln()
+        })
+    });
+    IvVector::from_iterator(ppm.nrows(), iv)
+}
+
+/// Rows of a probability matrix as plain numbers
+fn probabilities(ppm: &PpmMatrix) -> Vec<[f64; 4]> {
+    ppm.row_iter()
+        .map(|r| [0, 1, 2, 3].map(|b| f64::from(*r[b])))
+        .collect()
+}
+
+/// Lowest probability after adding pseudocounts, keeping the information vector finite
+const MIN_PROBABILITY: f32 = 1e-6;
+
+/// Converts rows to probabilities with pseudocounts. Frequency rows are scaled to `sites`
+/// first, count rows are used as they are.
+fn add_pseudocounts(
+    pwm: &mut PwmMatrixInner,
+    sites: Option<f32>,
+    policy: PseudocountPolicy,
+    pseudocount: f32,
+    background: &Background,
+) {
+    let background = background.frequencies().map(|q| q as f32);
+    for mut r in pwm.row_iter_mut() {
+        let sum = *r.sum();
+        let counts = [0, 1, 2, 3].map(|b| match (sites, sum > 0.0) {
+            (Some(sites), true) => *r[b] / sum * sites,
+            (Some(_), false) => 0.0,
+            (None, _) => *r[b],
+        });
+        let n: f32 = counts.iter().sum();
+        let (total, shares) = match policy {
+            PseudocountPolicy::Legacy | PseudocountPolicy::Fixed => (pseudocount, [0.25; 4]),
+            PseudocountPolicy::SqrtN => (n.sqrt(), [0.25; 4]),
+            PseudocountPolicy::Background => (pseudocount, background),
+        };
+        let values = [0, 1, 2, 3].map(|b| counts[b] + total * shares[b]);
+        let sum: f32 = values.iter().sum();
+        for (v, value) in r.iter_mut().zip(values) {
+            let p = match sum > 0.0 {
+                true => (value / sum).max(MIN_PROBABILITY),
+                false => 0.25,
+            };
+            *v = Float::new(p).expect("Guaranteed to be a number");
+        }
+    }
 }
 
+/// Legacy MATCH conversion, `(100 v + 1)` normalised per row
 fn to_ppm(pwm: &mut PwmMatrixInner) {
     const FLOAT_1: Float = unsafe { Float::new_unchecked(1.0) };
     const FLOAT_100: Float = unsafe { Float::new_unchecked(100.0) };
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use nom::branch::alt;
+use nom::character::complete::{char, line_ending, not_line_ending, one_of, space0, space1};
+use nom::combinator::{all_consuming, map, map_res};
+use nom::multi::{many1, separated_list0};
+use nom::number::complete::double;
+use nom::sequence::{preceded, tuple};
+use nom::IResult;
+use thiserror::Error;
+
+use crate::background::Background;
+use crate::sequence::Base;
+
+#[derive(Debug, Clone)]
+pub enum BackgroundTag {
+    /// A word and its probability
+    Word(Vec<Base>, f64),
+    Ignore,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum BackgroundParseError {
+    #[error("No word probabilities found")]
+    NoWords,
+    #[error("Word {0} missing")]
+    MissingWord(String),
+    #[error("Word {0} found twice")]
+    DuplicateWord(String),
+    #[error("Probability of word {0} is not positive")]
+    NotPositive(String),
+}
+
+impl TryFrom<Vec<BackgroundTag>> for Background {
+    type Error = BackgroundParseError;
+
+    fn try_from(value: Vec<BackgroundTag>) -> Result<Self, Self::Error> {
+        let name = |w: &[Base]| w.iter().map(Base::to_string).collect();
+        let len = value
+            .iter()
+            .filter_map(|t| match t {
+                BackgroundTag::Word(w, _) => Some(w.len()),
+                BackgroundTag::Ignore => None,
+            })
+            .max()
+            .ok_or(BackgroundParseError::NoWords)?;
+
+        let mut words: Vec<Vec<Option<f64>>> = (1..=len as u32)
+            .map(|k| vec![None; 4usize.pow(k)])
+            .collect();
+        for tag in value {
+            let BackgroundTag::Word(w, p) = tag else {
+                continue;
+            };
+            if p <= 0.0 {
+                return Err(BackgroundParseError::NotPositive(name(&w)));
+            }
+            let index = w.iter().fold(0, |i, b| i * 4 + *b as usize);
+            match &mut words[w.len() - 1][index] {
+                Some(_) => return Err(BackgroundParseError::DuplicateWord(name(&w))),
+                slot => *slot = Some(p),
+            }
+        }
+
+        let words = words
+            .into_iter()
+            .enumerate()
+            .map(|(k, w)| {
+                w.into_iter()
+                    .enumerate()
+                    .map(|(i, p)| {
+                        p.ok_or_else(|| BackgroundParseError::MissingWord(word(i, k + 1)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Background::from_word_probabilities(words).expect("Guaranteed to be complete"))
+    }
+}
+
+/// Letters of the word with `index` and `len` bases
+fn word(index: usize, len: usize) -> String {
+    (0..len)
+        .rev()
+        .map(|j| ['A', 'C', 'G', 'T'][index >> (2 * j) & 3])
+        .collect()
+}
+
+fn parse_line(input: &str) -> IResult<&str, BackgroundTag> {
+    all_consuming(alt((
+        map(
+            tuple((
+                many1(map_res(one_of("ACGT"), |c| Base::from_str(&c.to_string()))),
+                space1,
+                double,
+                space0,
+            )),
+            |(w, _, p, _)| BackgroundTag::Word(w, p),
+        ),
+        map(preceded(char('#'), not_line_ending), |_| {
+            BackgroundTag::Ignore
+        }),
+        map(space0, |_| BackgroundTag::Ignore),
+    )))(input.trim_start())
+}
+
+/// Parses a MEME background file, listing the probability of every word up to the order + 1
+/// like `AC 6.2e-02`, with `#` comments
+pub fn parse_background(input: &str) -> IResult<&str, Vec<BackgroundTag>> {
+    separated_list0(
+        line_ending,
+        map(map_res(not_line_ending, parse_line), |(_, t)| t),
+    )(input)
+}
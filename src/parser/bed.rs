@@ -0,0 +1,79 @@
+use std::io::{BufRead, Error, ErrorKind};
+
+use crate::parser::faidx::Region;
+
+/// An interval of a BED file. Only the first six columns are used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedRecord {
+    pub chrom: String,
+    /// 0-based start
+    pub start: u64,
+    /// 0-based exclusive end
+    pub end: u64,
+    pub name: Option<String>,
+    /// `Some(true)` for `+`, `Some(false)` for `-` and `None` if unstranded
+    pub strand: Option<bool>,
+}
+
+impl BedRecord {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<_> = line.split('\t').map(str::trim).collect();
+        if fields.len() < 3 {
+            return Err(format!("Expected at least 3 columns: {line}"));
+        }
+        let number = |f: &str| {
+            f.parse::<u64>()
+                .map_err(|_| format!("Invalid coordinate '{f}': {line}"))
+        };
+        let (start, end) = (number(fields[1])?, number(fields[2])?);
+        if end < start {
+            return Err(format!("End before start: {line}"));
+        }
+        let name = fields
+            .get(3)
+            .filter(|n| !n.is_empty() && **n != ".")
+            .map(|n| n.to_string());
+        let strand = match fields.get(5).copied() {
+            Some("+") => Some(true),
+            Some("-") => Some(false),
+            Some(".") | Some("") | None => None,
+            Some(s) => return Err(format!("Invalid strand '{s}': {line}")),
+        };
+
+        Ok(Self {
+            chrom: fields[0].to_string(),
+            start,
+            end,
+            name,
+            strand,
+        })
+    }
+}
+
+impl From<&BedRecord> for Region {
+    fn from(r: &BedRecord) -> Self {
+        Region {
+            name: r.chrom.clone(),
+            start: r.start,
+            end: Some(r.end),
+        }
+    }
+}
+
+/// Reads all intervals, skipping comments as well as `track` and `browser` lines
+pub fn read_bed<R: BufRead>(reader: R) -> std::io::Result<Vec<BedRecord>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| {
+            !matches!(l, Ok(l) if l.trim().is_empty()
+                || l.starts_with('#')
+                || l.starts_with("track")
+                || l.starts_with("browser"))
+        })
+        .map(|(i, l)| {
+            BedRecord::parse(&l?)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Line {}: {e}", i + 1)))
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use nom::error::ErrorKind;
+use nom::Offset;
+use thiserror::Error;
+
+use crate::parser::background::BackgroundParseError;
+use crate::parser::homer::HomerParseError;
+use crate::parser::jaspar::JasparParseError;
+use crate::parser::meme::MemeParseError;
+use crate::parser::transfac::TransfacParseError;
+
+/// Longest part of the offending line kept in a [`Location`]
+const MAX_CONTENT: usize = 100;
+
+/// Position of a parse error, with 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    /// The offending line, cut after 100 characters
+    pub content: String,
+}
+
+impl Location {
+    /// Location of `part`, which has to be a slice of `input`
+    pub fn new(input: &str, part: &str, path: Option<&Path>) -> Self {
+        let offset = input.offset(part);
+        let start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = input[start..].lines().next().unwrap_or_default();
+        Self {
+            path: path.map(Path::to_path_buf),
+            line: input[..start].matches('\n').count() + 1,
+            column: input[start..offset].chars().count() + 1,
+            content: line.chars().take(MAX_CONTENT).collect(),
+        }
+    }
+}
+
+impl Location {
+    /// Location of `part` within `content`, the line with 1-based number `line` of a file
+    /// that is read line by line
+    pub fn in_line(content: &str, part: &str, line: usize, path: Option<&Path>) -> Self {
+        Self {
+            line,
+            ..Self::new(content, part, path)
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        write!(f, "{}:{} `{}`", self.line, self.column, self.content)
+    }
+}
+
+/// Semantic problem of a single record
+#[derive(Debug, Clone, Error)]
+pub enum RecordError {
+    #[error(transparent)]
+    Transfac(#[from] TransfacParseError),
+    #[error(transparent)]
+    Meme(#[from] MemeParseError),
+    #[error(transparent)]
+    Jaspar(#[from] JasparParseError),
+    #[error(transparent)]
+    Homer(#[from] HomerParseError),
+    #[error(transparent)]
+    Background(#[from] BackgroundParseError),
+}
+
+/// A problem found while loading a file. It fails the load, or is collected as warning
+/// when the record is skipped in lenient mode.
+#[derive(Debug, Clone, Error)]
+pub enum ParseWarning {
+    #[error("Syntax error at {location}, expected {expected}")]
+    Syntax {
+        location: Location,
+        expected: String,
+    },
+    #[error("Invalid record at {location}: {error}")]
+    InvalidRecord {
+        location: Location,
+        error: RecordError,
+    },
+}
+
+impl ParseWarning {
+    /// Syntax error for a nom error within `input`
+    pub fn from_nom(
+        input: &str,
+        e: nom::Err<nom::error::Error<&str>>,
+        path: Option<&Path>,
+        expected: &str,
+    ) -> Self {
+        let (part, kind) = match &e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => (e.input, Some(e.code)),
+            nom::Err::Incomplete(_) => (&input[input.len()..], None),
+        };
+        let expected = match kind.and_then(describe) {
+            Some(token) => format!("{expected} ({token})"),
+            None => expected.to_string(),
+        };
+        ParseWarning::Syntax {
+            location: Location::new(input, part, path),
+            expected,
+        }
+    }
+
+    pub fn location(&self) -> &Location {
+        match self {
+            ParseWarning::Syntax { location, .. }
+            | ParseWarning::InvalidRecord { location, .. } => location,
+        }
+    }
+}
+
+fn describe(kind: ErrorKind) -> Option<&'static str> {
+    Some(match kind {
+        ErrorKind::Tag => "keyword",
+        ErrorKind::Float => "number",
+        ErrorKind::Digit => "digits",
+        ErrorKind::AlphaNumeric => "identifier",
+        ErrorKind::Space => "separator",
+        ErrorKind::CrLf | ErrorKind::Eof => "end of line",
+        _ => return None,
+    })
+}
+
+/// Splits `input` before every line for which `is_start` is true
+pub(crate) fn split_before<F: Fn(&str) -> bool>(input: &str, is_start: F) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    for line in input.split_inclusive('\n') {
+        let offset = input.offset(line);
+        if is_start(line.trim()) && offset > start {
+            records.push(&input[start..offset]);
+            start = offset;
+        }
+    }
+    records.push(&input[start..]);
+    records.retain(|r| !r.trim().is_empty());
+    records
+}
+
+/// Splits `input` after every line for which `is_end` is true
+pub(crate) fn split_after<F: Fn(&str) -> bool>(input: &str, is_end: F) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    for line in input.split_inclusive('\n') {
+        let end = input.offset(line) + line.len();
+        if is_end(line.trim()) {
+            records.push(&input[start..end]);
+            start = end;
+        }
+    }
+    records.push(&input[start..]);
+    records.retain(|r| !r.trim().is_empty());
+    records
+}
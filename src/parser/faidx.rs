@@ -0,0 +1,262 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::sequence::PlusStrand;
+
+/// One line of a samtools compatible `.fai` index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaiEntry {
+    pub name: String,
+    pub length: u64,
+    /// Byte offset of the first base
+    pub offset: u64,
+    pub line_bases: u64,
+    /// Bytes per line, including the line ending
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    /// Byte offset of the 0-based position `pos`
+    fn byte_offset(&self, pos: u64) -> u64 {
+        self.offset + pos / self.line_bases * self.line_width + pos % self.line_bases
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaiIndex {
+    pub entries: Vec<FaiEntry>,
+}
+
+impl FaiIndex {
+    /// Builds the index by scanning an uncompressed FASTA file
+    pub fn build<R: BufRead>(mut reader: R) -> std::io::Result<Self> {
+        fn invalid(msg: String) -> Error {
+            Error::new(ErrorKind::InvalidData, msg)
+        }
+
+        let mut entries: Vec<FaiEntry> = Vec::new();
+        let mut line = Vec::new();
+        let mut pos = 0;
+        // Set once a line shorter than `line_bases` was seen, which has to be the last one
+        let mut short_line = false;
+        loop {
+            line.clear();
+            let width = reader.read_until(b'\n', &mut line)? as u64;
+            if width == 0 {
+                break;
+            }
+            pos += width;
+
+            if let Some(header) = line.strip_prefix(b">") {
+                let header = String::from_utf8_lossy(header);
+                let name = header.split_whitespace().next().unwrap_or_default();
+                entries.push(FaiEntry {
+                    name: name.to_string(),
+                    length: 0,
+                    offset: pos,
+                    line_bases: 0,
+                    line_width: 0,
+                });
+                short_line = false;
+                continue;
+            }
+
+            let bases = line.trim_ascii_end().len() as u64;
+            let entry = match entries.last_mut() {
+                Some(entry) => entry,
+                None if bases == 0 => continue,
+                None => return Err(invalid("Sequence without header".to_string())),
+            };
+            if bases == 0 {
+                short_line = true;
+                continue;
+            }
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = width;
+            } else if short_line
+                || bases > entry.line_bases
+                || (line.ends_with(b"\n") && width - bases != entry.line_width - entry.line_bases)
+            {
+                return Err(invalid(format!(
+                    "Different line length in sequence '{}'",
+                    entry.name
+                )));
+            }
+            short_line |= bases < entry.line_bases;
+            entry.length += bases;
+        }
+        Ok(Self { entries })
+    }
+
+    /// Reads an existing `.fai` file
+    pub fn read<R: BufRead>(reader: R) -> std::io::Result<Self> {
+        let entries = reader
+            .lines()
+            .filter(|l| !matches!(l, Ok(l) if l.trim().is_empty()))
+            .map(|line| {
+                let line = line?;
+                let fields: Vec<_> = line.split('\t').collect();
+                let number = |i: usize| {
+                    fields
+                        .get(i)
+                        .and_then(|f| f.trim().parse::<u64>().ok())
+                        .ok_or_else(|| {
+                            Error::new(ErrorKind::InvalidData, format!("Invalid fai line: {line}"))
+                        })
+                };
+                let entry = FaiEntry {
+                    name: fields[0].to_string(),
+                    length: number(1)?,
+                    offset: number(2)?,
+                    line_bases: number(3)?,
+                    line_width: number(4)?,
+                };
+                // Positions are located by dividing by the number of bases per line
+                if entry.length > 0
+                    && (entry.line_bases == 0 || entry.line_width < entry.line_bases)
+                {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid line length in fai line: {line}"),
+                    ));
+                }
+                Ok(entry)
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for e in self.entries.iter() {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                e.name, e.length, e.offset, e.line_bases, e.line_width
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Builds the index of `path` and writes it to `path.fai`
+    pub fn build_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let index = Self::build(BufReader::new(File::open(&path)?))?;
+        index.write(File::create(fai_path(path))?)?;
+        Ok(index)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FaiEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+}
+
+fn fai_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut fai = path.as_ref().as_os_str().to_owned();
+    fai.push(".fai");
+    PathBuf::from(fai)
+}
+
+/// Part of a reference sequence, written as `chrom`, `chrom:start` or `chrom:start-end`
+/// with 1-based inclusive coordinates like samtools
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    /// 0-based start
+    pub start: u64,
+    /// 0-based exclusive end, `None` for the end of the sequence
+    pub end: Option<u64>,
+}
+
+impl FromStr for Region {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(ErrorKind::InvalidInput, format!("Invalid region: {s}"));
+        let parse = |n: &str| n.replace(',', "").parse::<u64>().map_err(|_| invalid());
+
+        let (name, range) = match s.rsplit_once(':') {
+            Some((name, range)) => (name, Some(range)),
+            None => (s, None),
+        };
+        let (start, end) = match range.map(|r| r.split_once('-')) {
+            None => (1, None),
+            Some(None) => (parse(range.unwrap_or_default())?, None),
+            Some(Some((start, end))) => (parse(start)?, Some(parse(end)?)),
+        };
+        if name.is_empty() || start == 0 || end.is_some_and(|e| e < start) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            start: start - 1,
+            end,
+        })
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}:{}-{}", self.name, self.start + 1, end),
+            None => write!(f, "{}:{}", self.name, self.start + 1),
+        }
+    }
+}
+
+/// Random access to regions of an uncompressed FASTA file
+pub struct IndexedFasta<R> {
+    reader: R,
+    pub index: FaiIndex,
+}
+
+impl IndexedFasta<BufReader<File>> {
+    /// Opens `path`, loading `path.fai` if it exists and indexing the file otherwise
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let index = match File::open(fai_path(&path)) {
+            Ok(fai) => FaiIndex::read(BufReader::new(fai))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                FaiIndex::build(BufReader::new(File::open(&path)?))?
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Self::new(BufReader::new(File::open(path)?), index))
+    }
+}
+
+impl<R: Read + Seek> IndexedFasta<R> {
+    pub fn new(reader: R, index: FaiIndex) -> Self {
+        Self { reader, index }
+    }
+
+    /// Fetches a region, clamped to the sequence length.
+    /// The returned strand is named after the region and offset by its start.
+    pub fn fetch(&mut self, region: &Region) -> std::io::Result<PlusStrand> {
+        let entry = self.index.get(&region.name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Sequence '{}' not in index", region.name),
+            )
+        })?;
+        let end = region.end.unwrap_or(entry.length).min(entry.length);
+        let start = region.start.min(end);
+
+        let mut raw = Vec::new();
+        if start < end {
+            let from = entry.byte_offset(start);
+            let to = entry.byte_offset(end - 1) + 1;
+            self.reader.seek(SeekFrom::Start(from))?;
+            (&mut self.reader).take(to - from).read_to_end(&mut raw)?;
+        }
+        raw.retain(|b| !b.is_ascii_whitespace());
+        let raw = String::from_utf8(raw).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        let mut seq = PlusStrand::from_str(&region.to_string(), &raw)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{e:?}")))?;
+        seq.offset = start as usize;
+        Ok(seq)
+    }
+}
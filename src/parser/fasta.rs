@@ -1,12 +1,11 @@
-use nom::branch::alt;
-use nom::bytes::complete::{tag, take_until};
-use nom::character::complete::{alpha1, multispace0, multispace1, not_line_ending, space1};
-use nom::combinator::{map, map_res, opt, rest};
-use nom::multi::{many0, separated_list0};
-use nom::sequence::{pair, tuple};
-use nom::IResult;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use crate::sequence::PlusStrand;
+use thiserror::Error;
+
+use crate::parser::error::{Location, ParseWarning};
+use crate::sequence::{Nucleotide, PlusStrand};
 
 #[derive(Debug, Clone)]
 pub struct Fasta {
@@ -22,39 +21,104 @@ impl TryFrom<Fasta> for PlusStrand {
     }
 }
 
-impl Fasta {
-    pub fn parse_many(input: &str) -> IResult<&str, Vec<Fasta>> {
-        many0(Self::parse)(input)
+#[derive(Debug, Error)]
+pub enum FastaError {
+    #[error("File access problem")]
+    Io(#[from] std::io::Error),
+    /// A record that can not be read, the reader continues after it
+    #[error(transparent)]
+    Invalid(#[from] ParseWarning),
+}
+
+/// Reads FASTA records one at a time, so only a single record is held in memory
+pub struct FastaReader<R> {
+    reader: R,
+    path: Option<PathBuf>,
+    line: String,
+    /// Number of lines read so far
+    line_number: usize,
+    name: Option<String>,
+    /// First problem of the current record, which is reported instead of the record
+    invalid: Option<ParseWarning>,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            path: None,
+            line: String::new(),
+            line_number: 0,
+            name: None,
+            invalid: None,
+        }
+    }
+
+    /// Path reported in the location of invalid records
+    pub fn with_path(mut self, path: Option<&Path>) -> Self {
+        self.path = path.map(Path::to_path_buf);
+        self
     }
 
-    pub fn parse(input: &str) -> IResult<&str, Fasta> {
-        fn parse_seq(input: &str) -> IResult<&str, Vec<&str>> {
-            map(
-                tuple((
-                    multispace0,
-                    separated_list0(multispace1, alpha1),
-                    multispace0,
-                )),
-                |(_, s, _)| s,
-            )(input)
+    fn syntax_error(&self, part: &str, expected: &str) -> ParseWarning {
+        let content = self.line.trim_end_matches(['\r', '\n']);
+        ParseWarning::Syntax {
+            location: Location::in_line(content, part, self.line_number, self.path.as_deref()),
+            expected: expected.to_string(),
         }
+    }
+
+    fn finish_record(&mut self, name: String, seq: String) -> Result<Fasta, FastaError> {
+        match self.invalid.take() {
+            Some(w) => Err(w.into()),
+            None => Ok(Fasta { name, seq }),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<Fasta, FastaError>;
 
-        map(
-            pair(
-                tuple((
-                    tag(">"),
-                    alt((take_until(" "), take_until("\t"), not_line_ending)),
-                    opt(pair(space1, not_line_ending)),
-                )),
-                map(
-                    map_res(alt((take_until(">"), rest)), parse_seq),
-                    |(_, s)| s.join(""),
-                ),
-            ),
-            |((_, name, _), seq)| Fasta {
-                name: name.to_string(),
-                seq,
-            },
-        )(input)
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut seq = String::new();
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    let name = self.name.take()?;
+                    return Some(self.finish_record(name, seq));
+                }
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            if let Some(header) = self.line.strip_prefix('>') {
+                let name = header
+                    .split([' ', '\t'])
+                    .next()
+                    .unwrap_or_default()
+                    .trim_end()
+                    .to_string();
+                if let Some(name) = self.name.replace(name) {
+                    return Some(self.finish_record(name, seq));
+                }
+            } else if self.name.is_some() {
+                let invalid = self.line.char_indices().find(|(_, c)| {
+                    !c.is_whitespace()
+                        && Nucleotide::from_str(&c.to_ascii_uppercase().to_string()).is_err()
+                });
+                match invalid {
+                    Some((i, _)) if self.invalid.is_none() => {
+                        self.invalid = Some(self.syntax_error(&self.line[i..], "nucleotide"));
+                    }
+                    _ => seq.extend(self.line.chars().filter(|c| !c.is_whitespace())),
+                }
+            } else if !self.line.trim().is_empty() {
+                let line = self.line.trim_start();
+                return Some(Err(self
+                    .syntax_error(line, "header starting with '>'")
+                    .into()));
+            }
+        }
     }
 }
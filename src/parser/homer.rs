@@ -0,0 +1,178 @@
+use std::io::Write;
+
+use nom::branch::alt;
+use nom::character::complete::{char, line_ending, not_line_ending, space1};
+use nom::combinator::{all_consuming, map, map_res, rest};
+use nom::multi::{separated_list0, separated_list1};
+use nom::number::complete::float;
+use nom::sequence::preceded;
+use nom::IResult;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use crate::background::Background;
+use crate::matrix::{gen_log_odds, Float, MatrixKind, MatrixMetadata, PwmMatrix, PwmMatrixInner};
+use crate::sequence::{Base, Nucleotide};
+
+#[derive(Debug, Clone)]
+pub enum HomerTag {
+    /// Tab separated header fields: consensus, name, log-odds threshold and optional statistics
+    Header(Vec<String>),
+    /// Probabilities of one position in A, C, G, T order
+    Row(Vec<Float>),
+    Ignore,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum HomerParseError {
+    #[error("No '>' header found")]
+    NoHeader,
+    #[error("Motif header has neither consensus nor name")]
+    NoName,
+    #[error("Motif {0} has no log-odds threshold")]
+    NoThreshold(String),
+    #[error("Invalid log-odds threshold '{1}' for motif {0}")]
+    InvalidThreshold(String, String),
+    #[error("Motif {0} has no rows")]
+    NoRows(String),
+    #[error("Motif {0} has a row with {1} instead of 4 values")]
+    WrongColumnCount(String, usize),
+}
+
+impl TryFrom<Vec<HomerTag>> for PwmMatrix {
+    type Error = HomerParseError;
+
+    fn try_from(value: Vec<HomerTag>) -> Result<Self, Self::Error> {
+        let header = value
+            .iter()
+            .find_map(|t| match t {
+                HomerTag::Header(fields) => Some(fields),
+                _ => None,
+            })
+            .ok_or(HomerParseError::NoHeader)?;
+        // Motifs without a name are known by their consensus
+        let name = header
+            .get(1)
+            .filter(|n| !n.is_empty())
+            .unwrap_or(&header[0])
+            .to_string();
+        if name.is_empty() {
+            return Err(HomerParseError::NoName);
+        }
+        let threshold = header
+            .get(2)
+            .ok_or_else(|| HomerParseError::NoThreshold(name.clone()))?;
+        let threshold = threshold
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| HomerParseError::InvalidThreshold(name.clone(), threshold.clone()))?;
+
+        let rows: Vec<_> = value
+            .iter()
+            .filter_map(|t| match t {
+                HomerTag::Row(values) => Some(values),
+                _ => None,
+            })
+            .collect();
+        if rows.is_empty() {
+            return Err(HomerParseError::NoRows(name));
+        }
+        if let Some(row) = rows.iter().find(|r| r.len() != 4) {
+            return Err(HomerParseError::WrongColumnCount(name, row.len()));
+        }
+        let values: Vec<_> = rows.into_iter().flatten().cloned().collect();
+
+        Ok(PwmMatrix {
+            name,
+            matrix: PwmMatrixInner::from_row_slice(&values),
+            kind: MatrixKind::Frequencies,
+            metadata: MatrixMetadata {
+                log_odds_threshold: Some(threshold),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+fn parse_line(input: &str) -> IResult<&str, HomerTag> {
+    let input = input.trim_end();
+    all_consuming(alt((
+        // Fields may be empty, e.g. a missing name
+        map(preceded(char('>'), rest), |fields: &str| {
+            HomerTag::Header(fields.split('\t').map(str::to_string).collect())
+        }),
+        map(
+            separated_list1(space1, map_res(float, Float::new)),
+            HomerTag::Row,
+        ),
+        map(rest, |_| HomerTag::Ignore),
+    )))(input.trim_start())
+}
+
+/// Parses a HOMER `.motif` file, grouping the lines of each motif
+pub fn parse_homer(input: &str) -> IResult<&str, Vec<Vec<HomerTag>>> {
+    map(
+        separated_list0(
+            line_ending,
+            map(map_res(not_line_ending, parse_line), |(_, t)| t),
+        ),
+        |tags| {
+            let mut groups: Vec<Vec<HomerTag>> = Vec::new();
+            for tag in tags {
+                match (&tag, groups.last_mut()) {
+                    (HomerTag::Ignore, _) => {}
+                    (HomerTag::Header(..), _) | (_, None) => groups.push(vec![tag]),
+                    (_, Some(group)) => group.push(tag),
+                }
+            }
+            groups
+        },
+    )(input)
+}
+
+/// Degenerate consensus of a probability row, as used in HOMER motif headers
+fn consensus(row: [f32; 4]) -> Nucleotide {
+    let mut order: Vec<_> = Base::iter().zip(row).collect();
+    order.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let (first, second) = (order[0], order[1]);
+    if first.1 >= 0.5 && first.1 >= 2.0 * second.1 {
+        return Nucleotide::from(first.0);
+    }
+    if first.1 + second.1 >= 0.75 {
+        return Nucleotide::iter()
+            .find(|n| {
+                n.bases().len() == 2 && [first.0, second.0].iter().all(|b| n.bases().contains(b))
+            })
+            .expect("Every pair of bases has an IUPAC code");
+    }
+    Nucleotide::N
+}
+
+/// Writes matrices in HOMER `.motif` format. Rows are normalised to probabilities.
+/// Matrices without a log-odds threshold get 60% of their maximal log-odds score.
+pub fn write_homer<W: Write>(mut writer: W, matrices: &[PwmMatrix]) -> std::io::Result<()> {
+    for pwm in matrices {
+        let rows: Vec<[f32; 4]> = pwm
+            .matrix
+            .row_iter()
+            .map(|r| {
+                let sum = *r.sum();
+                [0, 1, 2, 3].map(|i| if sum > 0.0 { *r[i] / sum } else { 0.25 })
+            })
+            .collect();
+        let threshold = pwm.metadata.log_odds_threshold.unwrap_or_else(|| {
+            let max: f32 = gen_log_odds(&pwm.matrix, &Background::default())
+                .row_iter()
+                .map(|r| **r.iter().max().expect("Guaranteed to not be 'None'"))
+                .sum();
+            0.6 * max
+        });
+        let consensus: String = rows.iter().map(|r| consensus(*r).to_string()).collect();
+
+        writeln!(writer, ">{consensus}\t{}\t{threshold}", pwm.name)?;
+        for r in rows.iter() {
+            writeln!(writer, "{:.3}\t{:.3}\t{:.3}\t{:.3}", r[0], r[1], r[2], r[3])?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, ErrorKind, Read};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Opens a file, transparently decompressing gzip and BGZF content
+pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Box<dyn BufRead>> {
+    decompress(File::open(path)?)
+}
+
+/// Wraps a reader and decompresses it if it starts with the gzip magic bytes.
+/// BGZF files are a series of gzip members and are decompressed as a whole.
+pub fn decompress<'a, R: Read + 'a>(mut reader: R) -> std::io::Result<Box<dyn BufRead + 'a>> {
+    let mut magic = [0u8; 2];
+    let mut n = 0;
+    while n < magic.len() {
+        match reader.read(&mut magic[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let reader = Cursor::new(magic[..n].to_vec()).chain(reader);
+
+    if magic[..n] == GZIP_MAGIC {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(BufReader::new(reader)))
+    }
+}
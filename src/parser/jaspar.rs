@@ -0,0 +1,186 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use nalgebra::DVector;
+use nom::branch::alt;
+use nom::bytes::complete::take_till1;
+use nom::character::complete::{char, line_ending, not_line_ending, one_of, space0, space1};
+use nom::combinator::{all_consuming, map, map_res, opt, rest};
+use nom::multi::{separated_list0, separated_list1};
+use nom::number::complete::float;
+use nom::sequence::{delimited, preceded, tuple};
+use nom::IResult;
+use thiserror::Error;
+
+use crate::matrix::{Float, MatrixKind, MatrixMetadata, PwmMatrix, PwmMatrixInner};
+use crate::sequence::Base;
+
+#[derive(Debug, Clone)]
+pub enum JasparTag {
+    /// Matrix ID and TF name
+    Header(String, Option<String>),
+    /// Counts of one base over all positions, the base is missing in bare `.pfm` files
+    Row(Option<Base>, Vec<Float>),
+    Ignore,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum JasparParseError {
+    #[error("No '>' header found")]
+    NoId,
+    #[error("Expected 4 rows, found {0}")]
+    WrongRowCount(usize),
+    #[error("Row for base {0} missing")]
+    MissingBase(Base),
+    #[error("Row for base {0} found twice")]
+    DuplicateBase(Base),
+    #[error("Rows have different lengths")]
+    UnequalLength,
+}
+
+impl TryFrom<Vec<JasparTag>> for PwmMatrix {
+    type Error = JasparParseError;
+
+    fn try_from(value: Vec<JasparTag>) -> Result<Self, Self::Error> {
+        let (name, factor) = value
+            .iter()
+            .find_map(|t| match t {
+                JasparTag::Header(id, factor) => Some((id.clone(), factor.clone())),
+                _ => None,
+            })
+            .ok_or(JasparParseError::NoId)?;
+
+        let rows: Vec<_> = value
+            .iter()
+            .filter_map(|t| match t {
+                JasparTag::Row(base, values) => Some((*base, values)),
+                _ => None,
+            })
+            .collect();
+        if rows.len() != 4 {
+            return Err(JasparParseError::WrongRowCount(rows.len()));
+        }
+        let ncols = rows[0].1.len();
+        if rows.iter().any(|(_, v)| v.len() != ncols) {
+            return Err(JasparParseError::UnequalLength);
+        }
+
+        let mut matrix = PwmMatrixInner::zeros(ncols);
+        let mut seen = [false; 4];
+        // Unlabeled rows are given in A, C, G, T order
+        for (i, (base, values)) in rows.iter().enumerate() {
+            let column = base.map_or(i, |b| b as usize);
+            if std::mem::replace(&mut seen[column], true) {
+                return Err(JasparParseError::DuplicateBase(base.unwrap_or(BASES[i])));
+            }
+            matrix.set_column(column, &DVector::from_column_slice(values));
+        }
+        if let Some(i) = seen.iter().position(|s| !s) {
+            return Err(JasparParseError::MissingBase(BASES[i]));
+        }
+
+        Ok(PwmMatrix {
+            name,
+            kind: MatrixKind::Counts,
+            matrix,
+            metadata: MatrixMetadata {
+                factor,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+const BASES: [Base; 4] = [Base::A, Base::C, Base::G, Base::T];
+
+fn parse_values(input: &str) -> IResult<&str, Vec<Float>> {
+    separated_list1(space1, map_res(float, Float::new))(input)
+}
+
+fn parse_line(input: &str) -> IResult<&str, JasparTag> {
+    let input = input.trim();
+    all_consuming(alt((
+        map(
+            tuple((
+                char('>'),
+                take_till1(|c: char| c.is_whitespace()),
+                opt(preceded(space1, rest)),
+            )),
+            |(_, id, factor): (_, &str, Option<&str>)| {
+                JasparTag::Header(id.to_string(), factor.map(|f| f.trim().to_string()))
+            },
+        ),
+        map(
+            tuple((
+                map_res(one_of("ACGT"), |b| Base::from_str(&b.to_string())),
+                space0,
+                alt((
+                    delimited(
+                        tuple((char('['), space0)),
+                        parse_values,
+                        tuple((space0, char(']'))),
+                    ),
+                    parse_values,
+                )),
+            )),
+            |(base, _, values)| JasparTag::Row(Some(base), values),
+        ),
+        map(parse_values, |values| JasparTag::Row(None, values)),
+        map(rest, |_| JasparTag::Ignore),
+    )))(input)
+}
+
+/// Parses `.jaspar` files as well as bare `.pfm` files, grouping the lines of each matrix.
+/// Bare `.pfm` files have no header and therefore yield a group without [`JasparTag::Header`].
+pub fn parse_jaspar(input: &str) -> IResult<&str, Vec<Vec<JasparTag>>> {
+    map(
+        separated_list0(
+            line_ending,
+            map(map_res(not_line_ending, parse_line), |(_, t)| t),
+        ),
+        |tags| {
+            let mut groups: Vec<Vec<JasparTag>> = Vec::new();
+            for tag in tags {
+                match (&tag, groups.last_mut()) {
+                    (JasparTag::Ignore, _) => {}
+                    (JasparTag::Header(..), _) | (_, None) => groups.push(vec![tag]),
+                    (_, Some(group)) => group.push(tag),
+                }
+            }
+            groups
+        },
+    )(input)
+}
+
+fn write_rows<W: Write>(mut writer: W, pwm: &PwmMatrix, labeled: bool) -> std::io::Result<()> {
+    for (base, column) in BASES.iter().zip(pwm.matrix.column_iter()) {
+        if labeled {
+            write!(writer, "{base}  [")?;
+        }
+        for v in column.iter() {
+            write!(writer, " {:>6}", v)?;
+        }
+        if labeled {
+            write!(writer, " ]")?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes matrices in `.jaspar` format, using the TF name from the metadata if present
+pub fn write_jaspar<W: Write>(mut writer: W, matrices: &[PwmMatrix]) -> std::io::Result<()> {
+    for pwm in matrices {
+        match &pwm.metadata.factor {
+            Some(factor) => writeln!(writer, ">{}\t{factor}", pwm.name)?,
+            None => writeln!(writer, ">{}", pwm.name)?,
+        }
+        write_rows(&mut writer, pwm, true)?;
+    }
+    Ok(())
+}
+
+/// Writes a single matrix as bare `.pfm` file without header
+pub fn write_pfm<W: Write>(writer: W, pwm: &PwmMatrix) -> std::io::Result<()> {
+    write_rows(writer, pwm, false)
+}
@@ -0,0 +1,301 @@
+use std::io::Write;
+
+use nalgebra::Matrix1x4;
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take_till1};
+use nom::character::complete::{alpha1, char, line_ending, not_line_ending, space0, space1};
+use nom::combinator::{all_consuming, map, map_res, opt, rest};
+use nom::multi::{many0, separated_list0, separated_list1};
+use nom::number::complete::float;
+use nom::sequence::{pair, preceded, separated_pair, tuple};
+use nom::IResult;
+use thiserror::Error;
+
+use crate::matrix::{Float, MatrixKind, MatrixMetadata, PwmMatrix, PwmMatrixInner};
+
+/// Order of the letters in every MEME DNA file
+const ALPHABET: &str = "ACGT";
+
+#[derive(Debug, Clone)]
+pub enum MemeTag {
+    Version(String),
+    Alphabet(String),
+    Strands(String),
+    Background,
+    Frequencies(Vec<(String, Float)>),
+    Motif(String, Option<String>),
+    LetterProbability(Vec<(String, String)>),
+    LogOdds,
+    Row(Vec<Float>),
+    Url(String),
+    Ignore,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum MemeParseError {
+    #[error("Unsupported alphabet: {0}")]
+    UnsupportedAlphabet(String),
+    #[error("Invalid background frequencies")]
+    InvalidBackground,
+    #[error("No 'letter-probability matrix' found for motif {0}")]
+    NoMatrix(String),
+    #[error("Invalid attribute '{1}' for motif {0}")]
+    InvalidAttribute(String, String),
+    #[error("Motif {0} has {1} rows, expected {2}")]
+    WrongRowCount(String, usize, usize),
+    #[error("Motif {0} has a row with {1} instead of 4 values")]
+    WrongColumnCount(String, usize),
+}
+
+/// Content of a MEME minimal motif file
+#[derive(Debug, Clone, Default)]
+pub struct MemeFile {
+    pub version: Option<String>,
+    pub strands: Option<String>,
+    /// Background letter frequencies in A, C, G, T order
+    pub background: Option<[Float; 4]>,
+    pub matrices: Vec<PwmMatrix>,
+}
+
+impl TryFrom<Vec<MemeTag>> for MemeFile {
+    type Error = MemeParseError;
+
+    fn try_from(value: Vec<MemeTag>) -> Result<Self, Self::Error> {
+        let mut file = MemeFile::default();
+        // Motif currently read, together with the expected and already read rows
+        let mut motif: Option<(PwmMatrix, Option<usize>, Vec<Float>)> = None;
+        let mut in_background = false;
+        let mut in_matrix = false;
+
+        for tag in value {
+            if !matches!(tag, MemeTag::Frequencies(_)) {
+                in_background = false;
+            }
+            if !matches!(tag, MemeTag::Row(_)) {
+                in_matrix = false;
+            }
+
+            match tag {
+                MemeTag::Version(v) => file.version = Some(v),
+                MemeTag::Alphabet(a) if a == ALPHABET => {}
+                MemeTag::Alphabet(a) => return Err(MemeParseError::UnsupportedAlphabet(a)),
+                MemeTag::Strands(s) => file.strands = Some(s),
+                MemeTag::Background => in_background = true,
+                MemeTag::Frequencies(f) if in_background => {
+                    let mut background = file.background.unwrap_or_default();
+                    for (letter, freq) in f {
+                        let i = ALPHABET
+                            .find(letter.as_str())
+                            .filter(|_| letter.len() == 1)
+                            .ok_or(MemeParseError::InvalidBackground)?;
+                        background[i] = freq;
+                    }
+                    file.background = Some(background);
+                    in_background = true;
+                }
+                MemeTag::Motif(id, factor) => {
+                    if let Some(m) = motif.take() {
+                        file.matrices.push(finish_motif(m)?);
+                    }
+                    let pwm = PwmMatrix {
+                        name: id,
+                        matrix: PwmMatrixInner::zeros(0),
+                        kind: MatrixKind::Frequencies,
+                        metadata: MatrixMetadata {
+                            factor,
+                            ..Default::default()
+                        },
+                    };
+                    motif = Some((pwm, None, Vec::new()));
+                }
+                MemeTag::LetterProbability(attributes) => {
+                    let (pwm, width, _) = match motif.as_mut() {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    for (key, value) in attributes {
+                        let invalid =
+                            || MemeParseError::InvalidAttribute(pwm.name.clone(), key.clone());
+                        match key.as_str() {
+                            "alength" if value != "4" => {
+                                return Err(MemeParseError::UnsupportedAlphabet(value))
+                            }
+                            "w" => *width = Some(value.parse().map_err(|_| invalid())?),
+                            "nsites" => {
+                                pwm.metadata.nsites = Some(value.parse().map_err(|_| invalid())?)
+                            }
+                            "E" => {
+                                pwm.metadata.e_value = Some(value.parse().map_err(|_| invalid())?)
+                            }
+                            _ => {}
+                        }
+                    }
+                    in_matrix = true;
+                }
+                MemeTag::Row(values) if in_matrix => {
+                    if let Some((pwm, width, rows)) = motif.as_mut() {
+                        if values.len() != 4 {
+                            return Err(MemeParseError::WrongColumnCount(
+                                pwm.name.clone(),
+                                values.len(),
+                            ));
+                        }
+                        rows.extend(values);
+                        in_matrix = width.is_none_or(|w| rows.len() < w * 4);
+                    }
+                }
+                MemeTag::Url(url) => {
+                    if let Some((pwm, _, _)) = motif.as_mut() {
+                        pwm.metadata.url = Some(url);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(m) = motif.take() {
+            file.matrices.push(finish_motif(m)?);
+        }
+
+        Ok(file)
+    }
+}
+
+fn finish_motif(
+    (mut pwm, width, rows): (PwmMatrix, Option<usize>, Vec<Float>),
+) -> Result<PwmMatrix, MemeParseError> {
+    if rows.is_empty() {
+        return Err(MemeParseError::NoMatrix(pwm.name));
+    }
+    let nrows = rows.len() / 4;
+    if let Some(w) = width.filter(|w| *w != nrows) {
+        return Err(MemeParseError::WrongRowCount(pwm.name, nrows, w));
+    }
+    pwm.matrix = PwmMatrixInner::from_row_slice(&rows);
+    Ok(pwm)
+}
+
+fn parse_line(input: &str) -> IResult<&str, MemeTag> {
+    let input = input.trim();
+    let word = || take_till1(|c: char| c.is_whitespace());
+    all_consuming(alt((
+        map(
+            preceded(pair(tag("MEME version"), space1), rest),
+            |v: &str| MemeTag::Version(v.to_string()),
+        ),
+        map(preceded(pair(tag("ALPHABET="), space0), rest), |a: &str| {
+            MemeTag::Alphabet(a.to_string())
+        }),
+        map(preceded(pair(tag("strands:"), space0), rest), |s: &str| {
+            MemeTag::Strands(s.to_string())
+        }),
+        map(pair(tag("Background letter frequencies"), rest), |_| {
+            MemeTag::Background
+        }),
+        map(
+            tuple((tag("MOTIF"), space1, word(), opt(preceded(space1, rest)))),
+            |(_, _, id, factor): (_, _, &str, Option<&str>)| {
+                MemeTag::Motif(id.to_string(), factor.map(|f| f.trim().to_string()))
+            },
+        ),
+        map(
+            preceded(
+                tag("letter-probability matrix:"),
+                many0(preceded(
+                    space0,
+                    separated_pair(is_not("= \t"), pair(char('='), space0), is_not(" \t")),
+                )),
+            ),
+            |a: Vec<(&str, &str)>| {
+                MemeTag::LetterProbability(
+                    a.iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                )
+            },
+        ),
+        map(pair(tag("log-odds matrix:"), rest), |_| MemeTag::LogOdds),
+        map(preceded(pair(tag("URL"), space1), rest), |u: &str| {
+            MemeTag::Url(u.to_string())
+        }),
+        map(
+            separated_list1(space1, map_res(float, Float::new)),
+            MemeTag::Row,
+        ),
+        map(
+            separated_list1(
+                space1,
+                separated_pair(alpha1, space1, map_res(float, Float::new)),
+            ),
+            |f: Vec<(&str, Float)>| {
+                MemeTag::Frequencies(f.iter().map(|(l, v)| (l.to_string(), *v)).collect())
+            },
+        ),
+        map(rest, |_| MemeTag::Ignore),
+    )))(input)
+}
+
+pub fn parse_meme(input: &str) -> IResult<&str, Vec<MemeTag>> {
+    map(
+        separated_list0(
+            line_ending,
+            map(map_res(not_line_ending, parse_line), |(_, t)| t),
+        ),
+        |mut t| {
+            t.drain(..)
+                .filter(|t| !matches!(t, MemeTag::Ignore))
+                .collect()
+        },
+    )(input)
+}
+
+/// Writes matrices in MEME minimal format. Count matrices are normalised per row.
+pub fn write_meme<W: Write>(
+    mut writer: W,
+    matrices: &[PwmMatrix],
+    background: Option<[Float; 4]>,
+) -> std::io::Result<()> {
+    writeln!(writer, "MEME version 4\n")?;
+    writeln!(writer, "ALPHABET= {ALPHABET}\n")?;
+    writeln!(writer, "strands: + -\n")?;
+    writeln!(writer, "Background letter frequencies")?;
+    let background = background.map_or([0.25; 4], |b| b.map(f32::from));
+    for (letter, freq) in ALPHABET.chars().zip(background) {
+        write!(writer, "{letter} {freq} ")?;
+    }
+    writeln!(writer, "\n")?;
+
+    for pwm in matrices {
+        match &pwm.metadata.factor {
+            Some(factor) => writeln!(writer, "MOTIF {} {factor}", pwm.name)?,
+            None => writeln!(writer, "MOTIF {}", pwm.name)?,
+        }
+        let total = pwm.matrix.row_iter().map(|r| *r.sum()).fold(0.0, f32::max);
+        let nsites = pwm
+            .metadata
+            .nsites
+            .or_else(|| ((total - 1.0).abs() > 0.01).then_some(total));
+        write!(
+            writer,
+            "letter-probability matrix: alength= 4 w= {}",
+            pwm.matrix.nrows()
+        )?;
+        if let Some(nsites) = nsites {
+            write!(writer, " nsites= {nsites}")?;
+        }
+        writeln!(writer, " E= {}", pwm.metadata.e_value.unwrap_or(0.0))?;
+        for row in pwm.matrix.row_iter() {
+            let sum = *row.sum();
+            let row: Matrix1x4<f32> = row.map(|v| if sum > 0.0 { *v / sum } else { 0.25 });
+            writeln!(
+                writer,
+                " {:.6}\t{:.6}\t{:.6}\t{:.6}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+        if let Some(url) = &pwm.metadata.url {
+            writeln!(writer, "\nURL {url}")?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
@@ -1,3 +1,11 @@
+pub mod background;
+pub mod bed;
+pub mod error;
+pub mod faidx;
 pub mod fasta;
+pub mod homer;
+pub mod input;
+pub mod jaspar;
+pub mod meme;
 pub mod profile;
 pub mod transfac;
@@ -1,63 +1,216 @@
 use std::collections::HashMap;
+use std::io::Write;
 
 use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{alphanumeric1, line_ending, not_line_ending, space1};
-use nom::combinator::{map, map_res, opt};
-use nom::multi::many0;
+use nom::bytes::complete::{tag, take_till1};
+use nom::character::complete::{
+    char, digit1, line_ending, not_line_ending, one_of, space0, space1,
+};
+use nom::combinator::{all_consuming, map, map_res, opt, rest, verify};
+use nom::multi::separated_list0;
 use nom::number::complete::float;
-use nom::sequence::{terminated, tuple};
+use nom::sequence::{delimited, preceded, tuple};
 use nom::IResult;
 use ordered_float::NotNan;
+use strum::{Display, EnumString};
 
 use crate::matrix::Float;
 
 pub type Css = Float;
 pub type Mss = Float;
 
-#[derive(Debug)]
+/// Criterion the cutoffs of a profile entry were chosen by, given as leading field
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+pub enum CutoffType {
+    /// Minimise false negatives
+    #[default]
+    #[strum(serialize = "1")]
+    MinFn,
+    /// Minimise false positives
+    #[strum(serialize = "2")]
+    MinFp,
+    /// Minimise the sum of both
+    #[strum(serialize = "3")]
+    MinSum,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Profile {
+    pub cutoff: CutoffType,
     pub css: Float,
     pub mss: Float,
+    /// Matrix accession or ID
     pub id: String,
+    /// Matrix or factor name following the ID
+    pub factor: Option<String>,
+}
+
+/// A named section of a MATCH profile file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileSet {
+    pub name: String,
+    /// Further text lines of the section header
+    pub description: Option<String>,
+    pub min_length: Option<usize>,
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ProfileTag {
+    Entry(Profile),
+    MinLength(usize),
+    /// Free text of a section header
+    Text(String),
+    /// End of a section
+    End,
+    Ignore,
+}
+
+fn separator(input: &str) -> IResult<&str, &str> {
+    alt((delimited(space0, tag(","), space0), space1))(input)
 }
 
 impl Profile {
+    /// Parses an entry like `1,0.85,0.85,M00205,V$GRE_C`, fields may also be separated by spaces
     pub fn parse(input: &str) -> IResult<&str, Profile> {
         map(
             tuple((
-                tag("1"),
-                // delimited(space0, char(','), space0),
-                alt((space1::<&str, _>, tag(","))),
+                map_res(one_of("123"), |c| c.to_string().parse::<CutoffType>()),
+                separator,
                 map_res(float, NotNan::new),
-                alt((space1::<&str, _>, tag(","))),
+                separator,
                 map_res(float, NotNan::new),
-                alt((space1::<&str, _>, tag(","))),
-                alphanumeric1,
-                alt((space1::<&str, _>, tag(","))),
-                not_line_ending,
+                separator,
+                take_till1(|c: char| c == ',' || c.is_whitespace()),
+                opt(preceded(separator, not_line_ending)),
             )),
-            |(_, _, css, _, mss, _, _, _, id)| Profile {
+            |(cutoff, _, css, _, mss, _, id, factor)| Profile {
+                cutoff,
                 css,
                 mss,
                 id: id.to_string(),
+                factor: factor
+                    .map(|f: &str| f.trim().to_string())
+                    .filter(|f| !f.is_empty()),
             },
         )(input)
     }
 
+    /// Parses a whole profile file into a map of all entries, later sections overriding earlier ones
     pub fn parse_many(input: &str) -> IResult<&str, HashMap<String, (Css, Mss)>> {
+        map(ProfileSet::parse_many, |sets| {
+            sets.into_iter()
+                .flat_map(|s| s.profiles)
+                .map(|Profile { css, mss, id, .. }| (id, (css, mss)))
+                .collect()
+        })(input)
+    }
+}
+
+/// Parses a single line of a profile file
+pub fn parse_line(input: &str) -> IResult<&str, ProfileTag> {
+    let input = input.trim();
+    let digits = input.trim_start_matches(|c: char| c.is_ascii_digit());
+    if digits.len() < input.len() {
+        let mut entry = all_consuming(map(Profile::parse, ProfileTag::Entry));
+        // Comma separated lines have to be entries, while others starting with a digit may
+        // also be a lone number following the MATCH header or header text like "2009 release"
+        if digits.trim_start().starts_with(',') {
+            return entry(input);
+        }
+        let number = all_consuming(map(float, |_| ProfileTag::Ignore));
+        if let Ok(tag) = alt((number, entry))(input) {
+            return Ok(tag);
+        }
+    }
+    all_consuming(alt((
         map(
-            many0(map(
-                map_res(terminated(not_line_ending, opt(line_ending)), Self::parse),
-                |(_, m)| m,
-            )),
-            |mut p| {
-                p.drain(..)
-                    .map(|Profile { css, mss, id }| (id, (css, mss)))
-                    .collect()
-            },
+            preceded(
+                tuple((tag("MIN_LENGTH"), space1)),
+                map_res(digit1, str::parse),
+            ),
+            ProfileTag::MinLength,
+        ),
+        map(preceded(char('#'), rest), |_| ProfileTag::Ignore),
+        map(tag("//"), |_| ProfileTag::End),
+        map(verify(rest, |t: &str| !t.is_empty()), |t: &str| {
+            ProfileTag::Text(t.to_string())
+        }),
+        map(rest, |_| ProfileTag::Ignore),
+    )))(input)
+}
+
+impl ProfileSet {
+    fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.description.is_none() && self.profiles.is_empty()
+    }
+
+    /// Groups tags into sections ending at `//`.
+    /// The first text line of a section is its name, further ones form the description.
+    pub fn from_tags<I: IntoIterator<Item = ProfileTag>>(tags: I) -> Vec<ProfileSet> {
+        let mut sets = Vec::new();
+        let mut current = ProfileSet::default();
+        for tag in tags {
+            match tag {
+                ProfileTag::Entry(p) => current.profiles.push(p),
+                ProfileTag::MinLength(l) => current.min_length = Some(l),
+                ProfileTag::Text(t) if current.is_empty() => current.name = t,
+                ProfileTag::Text(t) => match &mut current.description {
+                    Some(d) => {
+                        d.push('\n');
+                        d.push_str(&t);
+                    }
+                    None => current.description = Some(t),
+                },
+                ProfileTag::End if !current.is_empty() => sets.push(std::mem::take(&mut current)),
+                ProfileTag::End | ProfileTag::Ignore => {}
+            }
+        }
+        if !current.is_empty() {
+            sets.push(current);
+        }
+        sets
+    }
+
+    pub fn parse_many(input: &str) -> IResult<&str, Vec<ProfileSet>> {
+        map(
+            separated_list0(
+                line_ending,
+                map(map_res(not_line_ending, parse_line), |(_, t)| t),
+            ),
+            ProfileSet::from_tags,
         )(input)
     }
+
+    pub fn get(&self, id: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.id == id)
+    }
+}
+
+/// Writes profile sets, each terminated by `//`
+pub fn write_profiles<W: Write>(mut writer: W, sets: &[ProfileSet]) -> std::io::Result<()> {
+    for set in sets {
+        if !set.name.is_empty() {
+            writeln!(writer, "{}", set.name)?;
+        }
+        if let Some(description) = &set.description {
+            for line in description.lines() {
+                writeln!(writer, "{line}")?;
+            }
+        }
+        if let Some(min_length) = set.min_length {
+            writeln!(writer, "MIN_LENGTH {min_length}")?;
+        }
+        for p in set.profiles.iter() {
+            write!(writer, "{},{},{},{}", p.cutoff, p.css, p.mss, p.id)?;
+            match &p.factor {
+                Some(factor) => writeln!(writer, ",{factor}")?,
+                None => writeln!(writer)?,
+            }
+        }
+        writeln!(writer, "//")?;
+    }
+    Ok(())
 }
 
 // #[test]
@@ -1,19 +1,38 @@
-use nalgebra::Matrix1x4;
+use std::cmp::Ordering;
+use std::io::Write;
+use std::str::FromStr;
+
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
-use nom::character::complete::{alphanumeric1, digit1, line_ending, not_line_ending, space1};
-use nom::combinator::{map, map_res, rest};
+use nom::character::complete::{
+    alpha1, alphanumeric1, digit1, line_ending, not_line_ending, space0, space1,
+};
+use nom::combinator::{all_consuming, map, map_res, opt, rest};
 use nom::multi::{many0, separated_list0};
 use nom::number::complete::float;
-use nom::sequence::{pair, separated_pair};
+use nom::sequence::{pair, preceded, separated_pair, tuple};
 use nom::IResult;
+use strum::IntoEnumIterator;
 use thiserror::Error;
 
-use crate::matrix::{Float, PwmMatrix, PwmMatrixInner};
+use crate::matrix::{BindingFactor, Float, MatrixKind, MatrixMetadata, PwmMatrix, PwmMatrixInner};
+use crate::sequence::Base;
 
 #[derive(Debug, Clone)]
 pub enum TransfacTag {
     Id(String),
+    Accession(String),
+    Name(String),
+    Description(String),
+    BindingFactor(String),
+    Basis(String),
+    Comment(String),
+    /// Metadata of other formats, kept in `CC  <key>: <value>` lines
+    Kind(MatrixKind),
+    Sites(f32),
+    EValue(f64),
+    Url(String),
+    LogOddsThreshold(f32),
     Po(Vec<String>),
     Row(usize, Vec<Float>),
     Ignore,
@@ -40,35 +59,82 @@ impl TransfacTag {
         }
         None
     }
+
+    pub fn get_binding_factor(&self) -> Option<BindingFactor> {
+        if let TransfacTag::BindingFactor(bf) = self {
+            return Some(BindingFactor::from_transfac(bf));
+        }
+        None
+    }
 }
 
-#[derive(Debug, Error)]
+impl BindingFactor {
+    /// Parses a `BF` line like `T00526 MyoD; Species: mouse, Mus musculus.`
+    pub fn from_transfac(line: &str) -> Self {
+        let (factor, species) = match line.split_once("Species:") {
+            Some((factor, species)) => (factor, Some(species)),
+            None => (line, None),
+        };
+        let factor = factor.trim().trim_end_matches(';');
+        let (id, name) = factor
+            .split_once(';')
+            .or_else(|| factor.split_once(char::is_whitespace))
+            .unwrap_or((factor, ""));
+
+        BindingFactor {
+            id: id.trim().to_string(),
+            name: name.trim().to_string(),
+            species: species.map(|s| s.trim().trim_end_matches('.').to_string()),
+        }
+    }
+}
+
+/// Joins all lines of a field which may span multiple lines, like `DE` or `CC`
+fn join_field<F: Fn(&TransfacTag) -> Option<&String>>(
+    tags: &[TransfacTag],
+    f: F,
+) -> Option<String> {
+    let lines: Vec<_> = tags.iter().filter_map(f).map(String::as_str).collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+#[derive(Debug, Clone, Error)]
 pub enum TransfacParseError {
     #[error("Encountered unexpected Base: {0}")]
     UnexpectedBase(String),
     #[error("No 'PO' found")]
     NoPo,
+    #[error("'PO' does not contain every base: {0:?}")]
+    IncompletePo(Vec<String>),
     #[error("No 'ID' found")]
     NoId,
     #[error("No value row found")]
     NoRows,
+    #[error("Row {0} missing")]
+    MissingRow(usize),
+    #[error("Row {0} found twice")]
+    DuplicateRow(usize),
+    #[error("Row {0} has {1} values, expected one per 'PO' column")]
+    WrongValueCount(usize, usize),
 }
 
 impl TryFrom<Vec<TransfacTag>> for PwmMatrix {
     type Error = TransfacParseError;
 
     fn try_from(value: Vec<TransfacTag>) -> Result<Self, Self::Error> {
-        // let po: Vec<_> = value
-        //     .iter()
-        //     .find_map(TransfacTag::get_po)
-        //     .ok_or(TransfacParseError::NoPo)?
-        //     .iter()
-        //     .map(|b| match Base::from_str(b) {
-        //         Ok(b) => Ok(b),
-        //         Err(_) => Err(TransfacParseError::UnexpectedBase(b.to_string())),
-        //     })
-        //     .collect::<Result<_, _>>()?;
-        // TODO check Base order and everything else
+        let po: Vec<_> = value
+            .iter()
+            .find_map(TransfacTag::get_po)
+            .ok_or(TransfacParseError::NoPo)?
+            .iter()
+            .map(|b| {
+                Base::from_str(b).map_err(|_| TransfacParseError::UnexpectedBase(b.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        if !Base::iter().all(|b| po.contains(&b)) || po.len() != 4 {
+            let po = po.iter().map(Base::to_string).collect();
+            return Err(TransfacParseError::IncompletePo(po));
+        }
 
         let name = value
             .iter()
@@ -76,19 +142,83 @@ impl TryFrom<Vec<TransfacTag>> for PwmMatrix {
             .ok_or(TransfacParseError::NoId)?
             .to_string();
 
-        let rows = value.iter().filter_map(TransfacTag::get_row);
-        let nrows = rows
-            .clone()
-            .max_by_key(|(i, _)| *i)
-            .ok_or(TransfacParseError::NoRows)?
-            .0;
-        let mut pwm = PwmMatrixInner::zeros(nrows);
+        let mut rows: Vec<_> = value.iter().filter_map(TransfacTag::get_row).collect();
+        if rows.is_empty() {
+            return Err(TransfacParseError::NoRows);
+        }
+        rows.sort_by_key(|(i, _)| *i);
+        let mut pwm = PwmMatrixInner::zeros(rows.len());
 
-        for (i, v) in rows {
-            pwm.set_row(i - 1, &Matrix1x4::from_iterator(v.iter().cloned()))
+        for (expected, (i, v)) in (1..).zip(rows) {
+            match i.cmp(&expected) {
+                Ordering::Less => return Err(TransfacParseError::DuplicateRow(i)),
+                Ordering::Greater => return Err(TransfacParseError::MissingRow(expected)),
+                Ordering::Equal => {}
+            }
+            if v.len() != po.len() {
+                return Err(TransfacParseError::WrongValueCount(i, v.len()));
+            }
+            for (b, v) in po.iter().zip(v.iter()) {
+                pwm[(i - 1, *b as usize)] = *v;
+            }
         }
 
-        Ok(PwmMatrix { name, matrix: pwm })
+        let kind = value
+            .iter()
+            .find_map(|t| match t {
+                TransfacTag::Kind(k) => Some(*k),
+                _ => None,
+            })
+            .unwrap_or(MatrixKind::Counts);
+        let metadata = MatrixMetadata {
+            factor: join_field(&value, |t| match t {
+                TransfacTag::Name(n) => Some(n),
+                _ => None,
+            }),
+            accession: join_field(&value, |t| match t {
+                TransfacTag::Accession(a) => Some(a),
+                _ => None,
+            }),
+            description: join_field(&value, |t| match t {
+                TransfacTag::Description(d) => Some(d),
+                _ => None,
+            }),
+            binding_factors: value
+                .iter()
+                .filter_map(TransfacTag::get_binding_factor)
+                .collect(),
+            basis: join_field(&value, |t| match t {
+                TransfacTag::Basis(b) => Some(b),
+                _ => None,
+            }),
+            comment: join_field(&value, |t| match t {
+                TransfacTag::Comment(c) => Some(c),
+                _ => None,
+            }),
+            nsites: value.iter().find_map(|t| match t {
+                TransfacTag::Sites(n) => Some(*n),
+                _ => None,
+            }),
+            e_value: value.iter().find_map(|t| match t {
+                TransfacTag::EValue(e) => Some(*e),
+                _ => None,
+            }),
+            url: value.iter().find_map(|t| match t {
+                TransfacTag::Url(u) => Some(u.clone()),
+                _ => None,
+            }),
+            log_odds_threshold: value.iter().find_map(|t| match t {
+                TransfacTag::LogOddsThreshold(t) => Some(*t),
+                _ => None,
+            }),
+        };
+
+        Ok(PwmMatrix {
+            name,
+            kind,
+            matrix: pwm,
+            metadata,
+        })
     }
 }
 
@@ -103,10 +233,52 @@ pub fn parse_id(input: &str) -> IResult<&str, TransfacTag> {
     )(input)
 }
 
+/// Parses a free text line starting with `key`
+fn parse_field<'a>(
+    key: &'static str,
+    f: fn(String) -> TransfacTag,
+) -> impl FnMut(&'a str) -> IResult<&'a str, TransfacTag> {
+    map(
+        separated_pair(tag(key), space1::<&str, _>, not_line_ending),
+        move |(_, v)| f(v.trim().to_string()),
+    )
+}
+
+/// Parses a `CC  <key>: <value>` line, written for metadata TRANSFAC has no field for
+fn parse_property<'a, T: FromStr>(
+    key: &'static str,
+    f: fn(T) -> TransfacTag,
+) -> impl FnMut(&'a str) -> IResult<&'a str, TransfacTag> {
+    map_res(
+        preceded(
+            tuple((tag("CC"), space1, tag(key), tag(":"), space0)),
+            not_line_ending,
+        ),
+        move |v: &str| v.trim().parse().map(f),
+    )
+}
+
+pub fn parse_metadata(input: &str) -> IResult<&str, TransfacTag> {
+    alt((
+        parse_property("kind", TransfacTag::Kind),
+        parse_property("sites", TransfacTag::Sites),
+        parse_property("E-value", TransfacTag::EValue),
+        parse_property("URL", TransfacTag::Url),
+        parse_property("log-odds threshold", TransfacTag::LogOddsThreshold),
+        parse_field("AC", TransfacTag::Accession),
+        parse_field("NA", TransfacTag::Name),
+        parse_field("DE", TransfacTag::Description),
+        parse_field("BF", TransfacTag::BindingFactor),
+        parse_field("BA", TransfacTag::Basis),
+        parse_field("CC", TransfacTag::Comment),
+    ))(input)
+}
+
+/// Parses the column header, given as `PO` or `P0`
 pub fn parse_po(input: &str) -> IResult<&str, TransfacTag> {
     map(
         pair(
-            tag("PO"),
+            alt((tag("PO"), tag("P0"))),
             many0(map(pair(space1, alphanumeric1::<&str, _>), |(_, n)| {
                 n.to_string()
             })),
@@ -115,29 +287,41 @@ pub fn parse_po(input: &str) -> IResult<&str, TransfacTag> {
     )(input)
 }
 
+/// Parses a row of values, ignoring an optional trailing consensus letter
 pub fn parse_row(input: &str) -> IResult<&str, TransfacTag> {
     map(
-        pair(
+        tuple((
             map_res(digit1::<&str, _>, |v| v.parse::<usize>()),
             many0(map_res(pair(space1, float), |(_, v)| Float::new(v))),
-        ),
-        |(index, values)| TransfacTag::Row(index, values),
+            opt(pair(space1, alpha1)),
+            space0,
+        )),
+        |(index, values, _, _)| TransfacTag::Row(index, values),
     )(input)
 }
 
 pub fn parse_matrix(input: &str) -> IResult<&str, Vec<TransfacTag>> {
     separated_list0(
         line_ending,
-        alt((parse_id, parse_po, parse_row, ignore_line)),
+        alt((parse_id, parse_metadata, parse_po, parse_row, ignore_line)),
     )(input)
 }
 
+/// What a line starting like `line` should have contained, if it could not be parsed
+pub fn expected(line: &str) -> &'static str {
+    match line.trim_start() {
+        l if l.starts_with(|c: char| c.is_ascii_digit()) => "position followed by numbers",
+        l if l.starts_with("PO") || l.starts_with("P0") => "base names",
+        _ => "TRANSFAC line",
+    }
+}
+
 pub fn parse_matrices(input: &str) -> IResult<&str, Vec<Vec<TransfacTag>>> {
     map(
         separated_list0(
             tag("//"),
             map(
-                map_res(alt((take_until("//"), rest)), parse_matrix),
+                map_res(alt((take_until("//"), rest)), all_consuming(parse_matrix)),
                 |(_, mut m)| {
                     m.drain(..)
                         .filter(|t| !matches!(t, TransfacTag::Ignore))
@@ -148,3 +332,70 @@ pub fn parse_matrices(input: &str) -> IResult<&str, Vec<Vec<TransfacTag>>> {
         |mut m| m.drain(..).filter(|m| !m.is_empty()).collect(),
     )(input)
 }
+
+fn write_field<W: Write>(mut writer: W, key: &str, value: &Option<String>) -> std::io::Result<()> {
+    if let Some(value) = value {
+        for line in value.lines() {
+            writeln!(writer, "{key}  {line}")?;
+        }
+        writeln!(writer, "XX")?;
+    }
+    Ok(())
+}
+
+/// Writes matrices as TRANSFAC records, each terminated by `//`.
+/// Values are written with the shortest representation that parses back to the same number.
+/// Metadata without a TRANSFAC field, like the kind of a frequency matrix or its number of
+/// sites, is written as `CC  <key>: <value>` lines which are read back by [`parse_matrices`].
+pub fn write_transfac<W: Write>(mut writer: W, matrices: &[PwmMatrix]) -> std::io::Result<()> {
+    for pwm in matrices {
+        let meta = &pwm.metadata;
+        write_field(&mut writer, "AC", &meta.accession)?;
+        writeln!(writer, "ID  {}\nXX", pwm.name)?;
+        write_field(&mut writer, "NA", &meta.factor)?;
+        write_field(&mut writer, "DE", &meta.description)?;
+        for bf in meta.binding_factors.iter() {
+            write!(writer, "BF  {}", bf.id)?;
+            if !bf.name.is_empty() {
+                write!(writer, "; {}", bf.name)?;
+            }
+            match &bf.species {
+                Some(species) => writeln!(writer, "; Species: {species}.")?,
+                None => writeln!(writer)?,
+            }
+        }
+        if !meta.binding_factors.is_empty() {
+            writeln!(writer, "XX")?;
+        }
+
+        writeln!(writer, "P0      A      C      G      T")?;
+        for (i, row) in pwm.matrix.row_iter().enumerate() {
+            write!(writer, "{:02}", i + 1)?;
+            for v in row.iter() {
+                write!(writer, " {:>6}", v)?;
+            }
+            writeln!(writer)?;
+        }
+        writeln!(writer, "XX")?;
+        write_field(&mut writer, "BA", &meta.basis)?;
+        let comment: Vec<_> = [
+            (pwm.kind != MatrixKind::Counts).then(|| format!("kind: {}", pwm.kind)),
+            meta.nsites.map(|n| format!("sites: {n}")),
+            meta.e_value.map(|e| format!("E-value: {e}")),
+            meta.url.as_ref().map(|u| format!("URL: {u}")),
+            meta.log_odds_threshold
+                .map(|t| format!("log-odds threshold: {t}")),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(meta.comment.clone())
+        .collect();
+        write_field(
+            &mut writer,
+            "CC",
+            &(!comment.is_empty()).then(|| comment.join("\n")),
+        )?;
+        writeln!(writer, "//")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use std::borrow::Borrow;
+use std::io;
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::builder::{AmbiguityPolicy, MaskPolicy, Tfp};
+use crate::matrix::{ExtendedTfpMatrix, MssScorer};
+use crate::sequence::{MinusStrand, PackedSequence, PlusStrand, Sequence};
+use crate::sink::HitSink;
+
+/// Compiled matrices with the settings they are scanned with, built once by
+/// [`crate::builder::TfpCalculator::compile`] and reusable for any number of sequences
+#[derive(Debug)]
+pub struct Scanner {
+    pub(crate) matrices: Vec<ExtendedTfpMatrix>,
+    pub(crate) ambiguity_policy: AmbiguityPolicy,
+    pub(crate) mask_policy: MaskPolicy,
+    pub(crate) chunk_length: usize,
+}
+
+impl Scanner {
+    /// Names of the compiled matrices, in scanning order
+    pub fn matrix_names(&self) -> Vec<&str> {
+        self.matrices.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    /// Hits on both strands of `seq`. Long sequences are scanned in chunks of windows, so
+    /// that they are spread over all threads. Every window belongs to the chunk it starts in,
+    /// so hits at chunk borders are found once. Hits are ordered by matrix, strand and window.
+    pub fn scan(&self, seq: &PlusStrand) -> Vec<Tfp> {
+        let strands = Strands::new(seq);
+        self.units(strands.len())
+            .into_par_iter()
+            .flat_map_iter(|unit| self.scan_unit(&strands, unit))
+            .collect()
+    }
+
+    /// Hits of all sequences, scanned in parallel and reported in the order of the sequences
+    pub fn scan_many<I>(&self, seqs: I) -> Vec<Tfp>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<PlusStrand> + Sync,
+    {
+        let seqs: Vec<_> = seqs.into_iter().collect();
+        seqs.par_iter()
+            .flat_map_iter(|s| self.scan(s.borrow()))
+            .collect()
+    }
+
+    /// Hits of all sequences in the order of [`Self::scan_many`], computed while iterating.
+    /// Only one sequence and the hits of one work unit per thread are held in memory, which
+    /// is bounded by the chunk length.
+    pub fn hits<I>(&self, seqs: I) -> Hits<'_, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<PlusStrand> + Sync,
+    {
+        Hits {
+            scanner: self,
+            seqs: seqs.into_iter(),
+            current: None,
+            buffer: Vec::new().into_iter(),
+        }
+    }
+
+    /// Passes the hits of all sequences to `sink` as they are found
+    pub fn scan_into<I, S>(&self, seqs: I, sink: &mut S) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<PlusStrand> + Sync,
+        S: HitSink + ?Sized,
+    {
+        for hit in self.hits(seqs) {
+            sink.write(hit)?;
+        }
+        sink.finish()
+    }
+
+    /// Work units of a sequence with `len` bases, in the order of their hits
+    fn units(&self, len: usize) -> Vec<Unit> {
+        let chunk_length = self.chunk_length.max(1);
+        self.matrices
+            .iter()
+            .enumerate()
+            .flat_map(|(m, matrix)| {
+                let windows = (len + 1).saturating_sub(matrix.width());
+                (0..2).flat_map(move |strand| {
+                    (0..windows)
+                        .step_by(chunk_length)
+                        .map(move |start| (m, strand, start..windows.min(start + chunk_length)))
+                })
+            })
+            .collect()
+    }
+
+    fn scan_unit<S: Borrow<PlusStrand>>(
+        &self,
+        strands: &Strands<S>,
+        (m, strand, windows): Unit,
+    ) -> Vec<Tfp> {
+        let (packed, seq) = strands.strand(strand);
+        find_significant_bases(
+            &self.matrices[m],
+            &seq,
+            packed,
+            windows,
+            self.ambiguity_policy,
+            self.mask_policy,
+        )
+    }
+}
+
+/// Index of the matrix and the strand, and the windows scanned together
+type Unit = (usize, usize, Range<usize>);
+
+/// Both strands of a sequence with their packed bases. The plus strand is only borrowed.
+struct Strands<S> {
+    plus: S,
+    minus: MinusStrand,
+    packed: [PackedSequence; 2],
+}
+
+impl<S: Borrow<PlusStrand>> Strands<S> {
+    fn new(plus: S) -> Self {
+        let minus = MinusStrand::from(plus.borrow());
+        let packed = [
+            PackedSequence::from(plus.borrow().seq.as_slice()),
+            PackedSequence::from(minus.seq.as_slice()),
+        ];
+        Self {
+            plus,
+            minus,
+            packed,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.minus.seq.len()
+    }
+
+    /// The plus strand for 0 and the minus strand for 1
+    fn strand(&self, strand: usize) -> (&PackedSequence, Sequence<'_>) {
+        let seq = match strand {
+            0 => Sequence::Plus(self.plus.borrow()),
+            _ => Sequence::Minus(&self.minus),
+        };
+        (&self.packed[strand], seq)
+    }
+}
+
+/// Lazy iterator over the hits of sequences, see [`Scanner::hits`]
+pub struct Hits<'a, I: Iterator> {
+    scanner: &'a Scanner,
+    seqs: I,
+    /// Sequence being scanned and its remaining work units
+    current: Option<(Strands<I::Item>, std::vec::IntoIter<Unit>)>,
+    buffer: std::vec::IntoIter<Tfp>,
+}
+
+impl<I> Iterator for Hits<'_, I>
+where
+    I: Iterator,
+    I::Item: Borrow<PlusStrand> + Sync,
+{
+    type Item = Tfp;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(hit) = self.buffer.next() {
+                return Some(hit);
+            }
+            match &mut self.current {
+                Some((strands, units)) if !units.as_slice().is_empty() => {
+                    // One unit per thread, scanned in parallel and reported in order
+                    let batch: Vec<_> = units.take(rayon::current_num_threads()).collect();
+                    let scanner = self.scanner;
+                    let strands = &*strands;
+                    let hits: Vec<_> = batch
+                        .into_par_iter()
+                        .map(|unit| scanner.scan_unit(strands, unit))
+                        .collect();
+                    self.buffer = hits.into_iter().flatten().collect::<Vec<_>>().into_iter();
+                }
+                _ => {
+                    let strands = Strands::new(self.seqs.next()?);
+                    let units = self.scanner.units(strands.len()).into_iter();
+                    self.current = Some((strands, units));
+                }
+            }
+        }
+    }
+}
+
+/// Hits among the windows starting in `windows`.
+/// `packed` has to hold the bases of `seq`, it provides the core of every window.
+fn find_significant_bases(
+    tfp: &ExtendedTfpMatrix,
+    seq: &Sequence<'_>,
+    packed: &PackedSequence,
+    windows: Range<usize>,
+    policy: AmbiguityPolicy,
+    mask_policy: MaskPolicy,
+) -> Vec<Tfp> {
+    let seq_name = seq.name();
+    let ppm_nrows = tfp.ppm.nrows();
+    // Cores without table are scored from the window itself
+    let mut cores = (!tfp.css_table.is_empty())
+        .then(|| packed.kmers_from(tfp.core_length, windows.start + tfp.core_start));
+
+    let significant_bases = seq.as_slice()[windows.start..windows.end + ppm_nrows - 1]
+        .windows(ppm_nrows)
+        .enumerate()
+        .map(|(i, w)| {
+            (
+                windows.start + i,
+                w,
+                cores.as_mut().and_then(Iterator::next).flatten(),
+            )
+        })
+        .filter_map(|(i, w, core)| Some((i, w, tfp.css_of_core(core, w, policy)?)))
+        .filter(|(_, _, css)| css >= &tfp.css_threshold);
+
+    let mut scorer = MssScorer::new(tfp, packed);
+    significant_bases
+        .filter_map(|(i, w, css)| Some((i, w, css, scorer.mss(i, w, policy)?)))
+        .filter(|(_, _, _, mss)| mss >= &tfp.mss_threshold)
+        .filter(|(_, w, _, _)| tfp.passes_log_odds(w, policy))
+        .filter_map(|(i, w, css, mss)| Some((i, w, css, mss, tfp.p_value(w, policy)?)))
+        .filter(|(_, _, _, _, p_value)| tfp.passes_p_value(*p_value))
+        .filter_map(|(i, w, css, mss, p_value)| {
+            // Positions always refer to the leftmost base of the site on the plus strand,
+            // while the site itself is reported in matrix orientation
+            let (strand, pos) = match seq {
+                Sequence::Plus(_) => (true, i),
+                Sequence::Minus(_) => (false, seq.len() - i - ppm_nrows),
+            };
+            let masked = seq.is_masked(pos, ppm_nrows);
+            if masked && mask_policy == MaskPolicy::Ignore {
+                return None;
+            }
+            let (pos, strand) = seq.to_reference(pos, ppm_nrows, strand);
+
+            Some(Tfp {
+                sequence: seq_name.to_string(),
+                matrix: tfp.name.to_string(),
+                factors: tfp.factors.clone(),
+                pos,
+                strand,
+                css,
+                mss,
+                p_value,
+                len: ppm_nrows,
+                site: w.iter().map(|b| b.to_string()).collect(),
+                masked,
+            })
+        })
+        .collect()
+}
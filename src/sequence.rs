@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::str::FromStr;
 
 use strum::{Display, EnumIter, EnumString, ParseError};
@@ -11,6 +12,16 @@ pub enum Base {
 }
 
 impl Base {
+    /// Base of a 2-bit code, only the lowest two bits are used
+    pub fn from_code(code: u64) -> Base {
+        match code & 0b11 {
+            0 => Base::A,
+            1 => Base::C,
+            2 => Base::G,
+            _ => Base::T,
+        }
+    }
+
     pub fn complement(self) -> Base {
         match self {
             Base::A => Base::T,
@@ -21,14 +32,115 @@ impl Base {
     }
 }
 
-#[derive(Debug)]
-pub enum Sequence {
-    Plus(PlusStrand),
-    Minus(MinusStrand),
+/// Full IUPAC nucleotide alphabet, including ambiguity codes
+#[derive(Debug, EnumIter, Clone, Copy, EnumString, Display, Hash, Eq, PartialEq)]
+pub enum Nucleotide {
+    A,
+    C,
+    G,
+    T,
+    /// A or G
+    R,
+    /// C or T
+    Y,
+    /// G or C
+    S,
+    /// A or T
+    W,
+    /// G or T
+    K,
+    /// A or C
+    M,
+    /// not A
+    B,
+    /// not C
+    D,
+    /// not G
+    H,
+    /// not T
+    V,
+    /// any base
+    N,
 }
 
-impl Sequence {
-    pub fn as_slice(&self) -> &[Base] {
+impl Nucleotide {
+    /// All bases this nucleotide may stand for
+    pub fn bases(self) -> &'static [Base] {
+        match self {
+            Nucleotide::A => &[Base::A],
+            Nucleotide::C => &[Base::C],
+            Nucleotide::G => &[Base::G],
+            Nucleotide::T => &[Base::T],
+            Nucleotide::R => &[Base::A, Base::G],
+            Nucleotide::Y => &[Base::C, Base::T],
+            Nucleotide::S => &[Base::C, Base::G],
+            Nucleotide::W => &[Base::A, Base::T],
+            Nucleotide::K => &[Base::G, Base::T],
+            Nucleotide::M => &[Base::A, Base::C],
+            Nucleotide::B => &[Base::C, Base::G, Base::T],
+            Nucleotide::D => &[Base::A, Base::G, Base::T],
+            Nucleotide::H => &[Base::A, Base::C, Base::T],
+            Nucleotide::V => &[Base::A, Base::C, Base::G],
+            Nucleotide::N => &[Base::A, Base::C, Base::G, Base::T],
+        }
+    }
+
+    /// Returns the base if this nucleotide is not ambiguous
+    pub fn as_base(self) -> Option<Base> {
+        match self {
+            Nucleotide::A => Some(Base::A),
+            Nucleotide::C => Some(Base::C),
+            Nucleotide::G => Some(Base::G),
+            Nucleotide::T => Some(Base::T),
+            _ => None,
+        }
+    }
+
+    pub fn is_ambiguous(self) -> bool {
+        self.as_base().is_none()
+    }
+
+    pub fn complement(self) -> Nucleotide {
+        match self {
+            Nucleotide::A => Nucleotide::T,
+            Nucleotide::C => Nucleotide::G,
+            Nucleotide::G => Nucleotide::C,
+            Nucleotide::T => Nucleotide::A,
+            Nucleotide::R => Nucleotide::Y,
+            Nucleotide::Y => Nucleotide::R,
+            Nucleotide::S => Nucleotide::S,
+            Nucleotide::W => Nucleotide::W,
+            Nucleotide::K => Nucleotide::M,
+            Nucleotide::M => Nucleotide::K,
+            Nucleotide::B => Nucleotide::V,
+            Nucleotide::D => Nucleotide::H,
+            Nucleotide::H => Nucleotide::D,
+            Nucleotide::V => Nucleotide::B,
+            Nucleotide::N => Nucleotide::N,
+        }
+    }
+}
+
+impl From<Base> for Nucleotide {
+    fn from(b: Base) -> Self {
+        match b {
+            Base::A => Nucleotide::A,
+            Base::C => Nucleotide::C,
+            Base::G => Nucleotide::G,
+            Base::T => Nucleotide::T,
+        }
+    }
+}
+
+/// One strand of a sequence, borrowed for scanning
+#[derive(Debug, Clone, Copy)]
+pub enum Sequence<'a> {
+    Plus(&'a PlusStrand),
+    Minus(&'a MinusStrand),
+}
+
+impl Sequence<'_> {
+    pub fn as_slice(&self) -> &[Nucleotide] {
         match self {
             Sequence::Plus(p) => p.seq.as_slice(),
             Sequence::Minus(m) => m.seq.as_slice(),
@@ -53,38 +165,242 @@ impl Sequence {
             Sequence::Minus(m) => &m.name,
         }
     }
+
+    /// Position of the first plus strand base within its reference sequence
+    pub fn offset(&self) -> usize {
+        match self {
+            Sequence::Plus(p) => p.offset,
+            Sequence::Minus(m) => m.offset,
+        }
+    }
+
+    pub fn reverse_complemented(&self) -> bool {
+        match self {
+            Sequence::Plus(p) => p.reverse_complemented,
+            Sequence::Minus(m) => m.reverse_complemented,
+        }
+    }
+
+    /// Maps a site given by its leftmost plus strand position and strand
+    /// to position and strand on the reference sequence
+    pub fn to_reference(&self, pos: usize, len: usize, strand: bool) -> (usize, bool) {
+        if self.reverse_complemented() {
+            (self.offset() + self.len() - pos - len, !strand)
+        } else {
+            (self.offset() + pos, strand)
+        }
+    }
+
+    /// Soft-masked regions, always in plus strand coordinates
+    pub fn mask(&self) -> &[Range<usize>] {
+        match self {
+            Sequence::Plus(p) => &p.mask,
+            Sequence::Minus(m) => &m.mask,
+        }
+    }
+
+    /// Checks whether `len` bases starting at plus strand position `pos` overlap a masked region
+    pub fn is_masked(&self, pos: usize, len: usize) -> bool {
+        let mask = self.mask();
+        let i = mask.partition_point(|r| r.end <= pos);
+        mask.get(i).is_some_and(|r| r.start < pos + len)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct PlusStrand {
     pub name: String,
-    pub seq: Vec<Base>,
+    pub seq: Vec<Nucleotide>,
+    /// Sorted, non-overlapping regions given in lowercase (soft-masked repeats)
+    pub mask: Vec<Range<usize>>,
+    /// Position of the first base within its reference sequence, e.g. for fetched regions
+    pub offset: usize,
+    /// Whether `seq` is the reverse complement of its reference region,
+    /// e.g. for minus strand BED intervals
+    pub reverse_complemented: bool,
 }
 
 impl PlusStrand {
     pub fn from_str(name: &str, seq: &str) -> Result<Self, ParseError> {
-        seq.chars()
-            .map(|c| Base::from_str(&c.to_string()))
-            .collect::<Result<Vec<_>, _>>()
-            .map(|seq| Self {
-                name: name.to_string(),
-                seq,
+        let mut mask: Vec<Range<usize>> = Vec::new();
+        let seq = seq
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if c.is_ascii_lowercase() {
+                    match mask.last_mut() {
+                        Some(r) if r.end == i => r.end += 1,
+                        _ => mask.push(i..i + 1),
+                    }
+                }
+                Nucleotide::from_str(&c.to_ascii_uppercase().to_string())
             })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            seq,
+            mask,
+            offset: 0,
+            reverse_complemented: false,
+        })
+    }
+
+    /// Reverse complements the sequence while keeping its reference coordinates
+    pub fn reverse_complement(self) -> Self {
+        let len = self.seq.len();
+        Self {
+            seq: self.seq.iter().rev().map(|b| b.complement()).collect(),
+            mask: self
+                .mask
+                .iter()
+                .rev()
+                .map(|r| len - r.end..len - r.start)
+                .collect(),
+            reverse_complemented: !self.reverse_complemented,
+            ..self
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct MinusStrand {
     pub name: String,
-    pub seq: Vec<Base>,
+    pub seq: Vec<Nucleotide>,
+    /// Soft-masked regions in plus strand coordinates
+    pub mask: Vec<Range<usize>>,
+    pub offset: usize,
+    pub reverse_complemented: bool,
 }
 
+/// Reverse complement of a [`PlusStrand`], read 5' to 3' on the minus strand
 impl From<&PlusStrand> for MinusStrand {
     fn from(p: &PlusStrand) -> Self {
-        // Self(p.0.iter().cloned().rev().collect())
         Self {
             name: p.name.clone(),
-            seq: p.seq.iter().cloned().rev().collect(),
+            seq: p.seq.iter().rev().map(|b| b.complement()).collect(),
+            mask: p.mask.clone(),
+            offset: p.offset,
+            reverse_complemented: p.reverse_complemented,
+        }
+    }
+}
+
+/// Bases stored with 2 bits each, ambiguous nucleotides are only kept as such
+#[derive(Debug, Clone, Default)]
+pub struct PackedSequence {
+    len: usize,
+    /// 32 bases per word, the first base in the lowest bits
+    bases: Vec<u64>,
+    /// One bit per position, set for ambiguous nucleotides
+    ambiguous: Vec<u64>,
+}
+
+impl From<&[Nucleotide]> for PackedSequence {
+    fn from(seq: &[Nucleotide]) -> Self {
+        let mut bases = vec![0; seq.len().div_ceil(32)];
+        let mut ambiguous = vec![0; seq.len().div_ceil(64)];
+        for (i, n) in seq.iter().enumerate() {
+            match n.as_base() {
+                Some(b) => bases[i / 32] |= (b as u64) << (2 * (i % 32)),
+                None => ambiguous[i / 64] |= 1 << (i % 64),
+            }
+        }
+        Self {
+            len: seq.len(),
+            bases,
+            ambiguous,
+        }
+    }
+}
+
+impl PackedSequence {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_ambiguous(&self, i: usize) -> bool {
+        self.ambiguous[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// Base at position `i`, `None` for ambiguous nucleotides
+    pub fn base(&self, i: usize) -> Option<Base> {
+        (!self.is_ambiguous(i)).then(|| Base::from_code(self.code(i)))
+    }
+
+    /// Replaces `codes` by the 2-bit codes of `range`, ambiguous nucleotides giving 0
+    pub fn unpack(&self, range: Range<usize>, codes: &mut Vec<u8>) {
+        codes.clear();
+        codes.extend(range.map(|i| (self.code(i) & 0b11) as u8));
+    }
+
+    fn code(&self, i: usize) -> u64 {
+        self.bases[i / 32] >> (2 * (i % 32))
+    }
+
+    /// Index of every word of `k` bases in base 4, with the first base most significant,
+    /// or `None` for words containing ambiguous nucleotides.
+    /// The index is updated for each position instead of reading all `k` bases again.
+    pub fn kmers(&self, k: usize) -> Kmers<'_> {
+        self.kmers_from(k, 0)
+    }
+
+    /// Like [`Self::kmers`], but starting with the word at position `start`
+    pub fn kmers_from(&self, k: usize, start: usize) -> Kmers<'_> {
+        assert!((1..=32).contains(&k), "Words have 1 to 32 bases");
+        let mut kmers = Kmers {
+            seq: self,
+            k,
+            mask: u64::MAX >> (64 - 2 * k),
+            next: start,
+            index: 0,
+            valid: 0,
+        };
+        for _ in 0..(k - 1).min(self.len.saturating_sub(start)) {
+            kmers.push();
+        }
+        kmers
+    }
+}
+
+/// Iterator over the word indices of a [`PackedSequence`], see [`PackedSequence::kmers`]
+#[derive(Debug, Clone)]
+pub struct Kmers<'a> {
+    seq: &'a PackedSequence,
+    k: usize,
+    mask: u64,
+    /// Position of the next base to add
+    next: usize,
+    index: u64,
+    /// Number of unambiguous bases before `next`
+    valid: usize,
+}
+
+impl Kmers<'_> {
+    fn push(&mut self) {
+        let i = self.next;
+        self.next += 1;
+        match self.seq.is_ambiguous(i) {
+            true => self.valid = 0,
+            false => self.valid += 1,
+        }
+        self.index = ((self.index << 2) | (self.seq.code(i) & 0b11)) & self.mask;
+    }
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = Option<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.seq.len {
+            return None;
         }
+        self.push();
+        Some((self.valid >= self.k).then_some(self.index as usize))
     }
 }
@@ -0,0 +1,77 @@
+use std::io::{self, Write};
+
+use crate::builder::Tfp;
+
+/// Destination of hits, which receives them one by one while scanning
+pub trait HitSink {
+    fn write(&mut self, hit: Tfp) -> io::Result<()>;
+
+    /// Called after the last hit
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl HitSink for Vec<Tfp> {
+    fn write(&mut self, hit: Tfp) -> io::Result<()> {
+        self.push(hit);
+        Ok(())
+    }
+}
+
+/// Writes hits as tab separated lines below a header line
+#[derive(Debug)]
+pub struct TsvSink<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> TsvSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header_written: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            self.header_written = true;
+            writeln!(
+                self.writer,
+                "sequence\tpos\tstrand\tmatrix\tfactors\tcss\tmss\tp_value\tlen\tsite\tmasked"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> HitSink for TsvSink<W> {
+    fn write(&mut self, hit: Tfp) -> io::Result<()> {
+        self.write_header()?;
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            hit.sequence,
+            hit.pos,
+            if hit.strand { '+' } else { '-' },
+            hit.matrix,
+            hit.factors.join(","),
+            hit.css,
+            hit.mss,
+            hit.p_value,
+            hit.len,
+            hit.site,
+            hit.masked
+        )
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        self.writer.flush()
+    }
+}
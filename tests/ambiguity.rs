@@ -0,0 +1,51 @@
+use tfp::builder::{AmbiguityPolicy, Tfp};
+
+mod common;
+
+fn evaluate(seq: &str, policy: AmbiguityPolicy) -> Vec<Tfp> {
+    let mut calculator = common::calculator_for(seq);
+    calculator.set_ambiguity_policy(policy);
+    calculator.evaluate().unwrap()
+}
+
+fn test1(hits: &[Tfp], pos: usize, strand: bool) -> Option<&Tfp> {
+    hits.iter()
+        .find(|t| t.matrix == "Test1" && t.pos == pos && t.strand == strand)
+}
+
+#[test]
+fn skip_windows_with_ambiguous_bases() {
+    let seq = "CACGTGNNNNNNNNCACGTG";
+    let hits = evaluate(seq, AmbiguityPolicy::Skip);
+    assert!(hits.iter().all(|t| !t.site.contains('N')));
+    assert!(test1(&hits, 0, true).is_some());
+    assert!(test1(&hits, 14, false).is_some());
+    assert!(test1(&hits, 1, true).is_none());
+}
+
+#[test]
+fn policies_bound_the_exact_scores() {
+    // R is A or G, Y is C or T
+    let ambiguous = "CACRTG";
+    let min = evaluate(ambiguous, AmbiguityPolicy::Min);
+    let avg = evaluate(ambiguous, AmbiguityPolicy::Average);
+    let max = evaluate(ambiguous, AmbiguityPolicy::Max);
+    let exact: Vec<_> = ["CACATG", "CACGTG"]
+        .iter()
+        .map(|s| evaluate(s, AmbiguityPolicy::Skip))
+        .collect();
+
+    for strand in [true, false] {
+        let min = test1(&min, 0, strand).unwrap();
+        let avg = test1(&avg, 0, strand).unwrap();
+        let max = test1(&max, 0, strand).unwrap();
+        let exact: Vec<_> = exact
+            .iter()
+            .map(|e| *test1(e, 0, strand).unwrap().mss)
+            .collect();
+        assert!((*min.mss - exact.iter().cloned().fold(f32::MAX, f32::min)).abs() < 1e-6);
+        assert!((*max.mss - exact.iter().cloned().fold(f32::MIN, f32::max)).abs() < 1e-6);
+        assert!((*avg.mss - (exact[0] + exact[1]) / 2.0).abs() < 1e-6);
+        assert_eq!(min.site, if strand { "CACRTG" } else { "CAYGTG" });
+    }
+}
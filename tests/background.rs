@@ -0,0 +1,88 @@
+use tfp::background::Background;
+use tfp::builder::{Tfp, TfpCalculator, TfpError};
+
+mod common;
+
+use common::calculator_for;
+
+fn find(hits: Vec<Tfp>, matrix: &str, pos: usize) -> Tfp {
+    hits.into_iter()
+        .find(|t| t.matrix == matrix && t.strand && t.pos == pos)
+        .unwrap()
+}
+
+#[test]
+fn meme_background_file() {
+    let mut calculator = calculator_for("CACGTG");
+    calculator
+        .set_background_from_meme_file("test_files/background.txt")
+        .unwrap();
+    let background = calculator.get_background();
+    assert_eq!(background.order(), 1);
+    assert_eq!(background.frequencies(), [0.2, 0.3, 0.3, 0.2]);
+    assert!((background.words(2).unwrap()[0b0110] - 0.03).abs() < 1e-12);
+
+    // P(C) P(A|C) P(C|A) P(G|C) P(T|G) P(G|T), as every position has a single best base
+    let hit = find(calculator.clone().evaluate().unwrap(), "Test1", 0);
+    let expected = 0.3 * 0.3 * 0.3 * 0.1 * 0.2 * 0.3;
+    assert!((hit.p_value - expected).abs() < 1e-9);
+}
+
+#[test]
+fn invalid_background_file() {
+    let mut calculator = TfpCalculator::default();
+    let missing = "A 0.25\nC 0.25\nG 0.25\nT 0.25\nAA 0.5\n";
+    assert!(matches!(
+        calculator.set_background_from_meme_reader(missing.as_bytes()),
+        Err(TfpError::InvalidRecord { .. })
+    ));
+    match calculator.set_background_from_meme_reader("# order 0\nA 0.3\nX 0.2\n".as_bytes()) {
+        Err(TfpError::Syntax { location, .. }) => assert_eq!(location.line, 3),
+        r => panic!("{r:?}"),
+    }
+    assert_eq!(calculator.get_background(), &Background::default());
+}
+
+#[test]
+fn estimate_from_sequences() {
+    let mut calculator = calculator_for("GCGCGGCCATGCGCNNGGCGCAT");
+    calculator.estimate_background(2);
+    let background = calculator.get_background();
+    assert_eq!(background.order(), 2);
+    assert_eq!(background.words(3).unwrap().len(), 64);
+
+    // Both strands are counted
+    let [a, c, g, t] = background.frequencies();
+    assert!((a - t).abs() < 1e-12 && (c - g).abs() < 1e-12);
+    assert!(c > a);
+}
+
+#[test]
+fn composition_changes_scores() {
+    let seq = "TTGATATCAATAATGA";
+    let mut calculator = calculator_for(seq);
+    let uniform = find(calculator.clone().evaluate().unwrap(), "Test2", 0);
+    calculator.set_background(Background::from_frequencies([0.4, 0.1, 0.1, 0.4]).unwrap());
+    let at_rich = find(calculator.clone().evaluate().unwrap(), "Test2", 0);
+
+    assert_ne!(uniform.mss, at_rich.mss);
+    // A-T rich windows are common in an A-T rich background
+    assert!(at_rich.p_value > uniform.p_value);
+}
+
+#[test]
+fn markov_background_changes_scores() {
+    let mut calculator = calculator_for("TTCACGTAAA");
+    let uniform = find(calculator.clone().evaluate().unwrap(), "Test1", 2);
+
+    // Uniform base composition, but CG is rare and GC common
+    let mut dinucleotides = vec![1.0 / 16.0; 16];
+    dinucleotides[0b0110] = 0.01;
+    dinucleotides[0b1001] = 2.0 / 16.0 - 0.01;
+    let background = Background::from_word_probabilities(vec![vec![0.25; 4], dinucleotides]);
+    calculator.set_background(background.unwrap());
+    let markov = find(calculator.clone().evaluate().unwrap(), "Test1", 2);
+
+    assert_eq!(markov.site, "CACGTA");
+    assert_ne!(markov.mss, uniform.mss);
+}
@@ -0,0 +1,72 @@
+use tfp::builder::Tfp;
+use tfp::parser::bed::{read_bed, BedRecord};
+
+mod common;
+
+const FASTA: &str = ">chr1\nTTGGAACGTGGGCCGATTTGTTTATTACCCAGT\nCACGTGtttt\n";
+
+#[test]
+fn parse_records() {
+    let bed = "track name=peaks\n# comment\nchr1\t5\t20\tpeak1\t0\t-\nchr2\t0\t10\n";
+    let records = read_bed(bed.as_bytes()).unwrap();
+    assert_eq!(
+        records,
+        vec![
+            BedRecord {
+                chrom: "chr1".to_string(),
+                start: 5,
+                end: 20,
+                name: Some("peak1".to_string()),
+                strand: Some(false),
+            },
+            BedRecord {
+                chrom: "chr2".to_string(),
+                start: 0,
+                end: 10,
+                name: None,
+                strand: None,
+            }
+        ]
+    );
+    assert!(read_bed("chr1\t20\t5\n".as_bytes()).is_err());
+}
+
+#[test]
+fn hits_in_chromosome_coordinates() {
+    let dir = std::env::temp_dir();
+    let fasta = dir.join("tfp_bed_hits.fa");
+    let bed = dir.join("tfp_bed_hits.bed");
+    std::fs::write(&fasta, FASTA).unwrap();
+    std::fs::write(&bed, "chr1\t3\t30\tplus\t0\t+\nchr1\t3\t30\tminus\t0\t-\n").unwrap();
+
+    let mut calculator = common::calculator();
+    let mut whole = calculator.clone();
+    calculator.add_from_bed_file(&bed, &fasta).unwrap();
+    whole.add_from_fasta_file(&fasta).unwrap();
+
+    let names: Vec<_> = calculator.sequences.iter().map(|s| &s.name).collect();
+    assert_eq!(names, vec!["plus", "minus"]);
+
+    let key = |t: &Tfp| (t.matrix.clone(), t.pos, t.strand, t.site.clone(), t.mss);
+    let whole: Vec<_> = whole.evaluate().unwrap().iter().map(key).collect();
+    let hits = calculator.evaluate().unwrap();
+    let mut plus: Vec<_> = hits
+        .iter()
+        .filter(|t| t.sequence == "plus")
+        .map(key)
+        .collect();
+    let mut minus: Vec<_> = hits
+        .iter()
+        .filter(|t| t.sequence == "minus")
+        .map(key)
+        .collect();
+    plus.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    minus.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    assert!(!plus.is_empty());
+    assert_eq!(plus, minus);
+    assert!(plus.iter().all(|p| p.1 >= 3 && whole.contains(p)));
+
+    std::fs::remove_file(fasta).unwrap();
+    std::fs::remove_file(bed).unwrap();
+}
@@ -0,0 +1,51 @@
+use ordered_float::NotNan;
+use tfp::builder::TfpCalculator;
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+use common::{random_genomic_sequence, summary, Lcg};
+
+fn calculator() -> TfpCalculator {
+    let mut calculator = common::calculator();
+    calculator
+        .add_from_meme_file("test_files/meme.txt")
+        .unwrap();
+    let mut rng = Lcg(0);
+    for (i, len) in [3000, 10, 700].into_iter().enumerate() {
+        let seq = random_genomic_sequence(&mut rng, len);
+        calculator.add_sequence(PlusStrand::from_str(&format!("seq{i}"), &seq).unwrap());
+    }
+    calculator.set_default_css_threshold(NotNan::new(0.6).unwrap());
+    calculator.set_default_mss_threshold(NotNan::new(0.6).unwrap());
+    calculator
+}
+
+#[test]
+fn chunks_give_the_same_hits() {
+    let mut calculator = calculator();
+    let expected = summary(&calculator.clone().evaluate().unwrap());
+    assert!(expected.len() > 100);
+    // Chunks of one window, chunks shorter and longer than the matrices and of a whole block
+    for chunk_length in [0, 1, 7, 25, 64, 1000] {
+        calculator.set_chunk_length(chunk_length);
+        assert_eq!(summary(&calculator.clone().evaluate().unwrap()), expected);
+    }
+}
+
+#[test]
+fn order_does_not_depend_on_threads() {
+    let mut calculator = calculator();
+    calculator.set_chunk_length(50);
+    let evaluate = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        summary(&pool.install(|| calculator.clone().evaluate().unwrap()))
+    };
+    let expected = evaluate(1);
+    for threads in [2, 3, 8] {
+        assert_eq!(evaluate(threads), expected);
+    }
+}
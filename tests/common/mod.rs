@@ -0,0 +1,71 @@
+//! Fixtures shared by the integration tests, each of which uses only some of them
+#![allow(dead_code)]
+
+use tfp::builder::{Tfp, TfpCalculator};
+use tfp::sequence::PlusStrand;
+
+/// Deterministic pseudo random numbers
+pub struct Lcg(pub u32);
+
+impl Lcg {
+    pub fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        self.0 >> 16
+    }
+}
+
+/// Random sequence of A, C, G and T
+pub fn random_sequence(rng: &mut Lcg, len: usize) -> String {
+    (0..len)
+        .map(|_| ['A', 'C', 'G', 'T'][rng.next() as usize % 4])
+        .collect()
+}
+
+/// Random sequence with about 1% ambiguous (`N`, `R`) and 4% soft-masked bases
+pub fn random_genomic_sequence(rng: &mut Lcg, len: usize) -> String {
+    (0..len)
+        .map(|_| match rng.next() % 200 {
+            199 => 'N',
+            198 => 'R',
+            r if r >= 190 => ['a', 'c', 'g', 't'][r as usize % 4],
+            r => ['A', 'C', 'G', 'T'][r as usize % 4],
+        })
+        .collect()
+}
+
+/// Calculator with the matrices of `test_files/transfac.txt` and thresholds of 0
+pub fn calculator() -> TfpCalculator {
+    let mut calculator = TfpCalculator::default();
+    calculator
+        .add_from_transfac_file("test_files/transfac.txt")
+        .unwrap();
+    calculator
+}
+
+/// [`calculator`] with `seq` as sequence named `test`
+pub fn calculator_for(seq: &str) -> TfpCalculator {
+    let mut calculator = calculator();
+    calculator.add_sequence(PlusStrand::from_str("test", seq).unwrap());
+    calculator
+}
+
+/// Everything reported about a hit but the site, with the scores as bits
+pub type Summary = (String, String, usize, bool, u32, u32, bool);
+
+/// Summaries of the hits, in the order they were reported
+pub fn summary(hits: &[Tfp]) -> Vec<Summary> {
+    hits.iter()
+        .map(|t| {
+            let (css, mss) = (t.css.to_bits(), t.mss.to_bits());
+            (
+                t.sequence.clone(),
+                t.matrix.clone(),
+                t.pos,
+                t.strand,
+                css,
+                mss,
+                t.masked,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tfp::builder::TfpCalculator;
+
+fn gzip(content: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(path).unwrap()
+}
+
+#[test]
+fn gzip_and_plain_inputs_are_equal() {
+    let mut plain = TfpCalculator::default();
+    plain
+        .add_from_transfac_reader(read("test_files/transfac.txt").as_slice())
+        .unwrap();
+    plain
+        .add_from_profile_reader(read("test_files/profile.txt").as_slice())
+        .unwrap();
+    plain
+        .add_from_fasta_reader(read("test_files/fasta.txt").as_slice())
+        .unwrap();
+
+    let mut compressed = TfpCalculator::default();
+    compressed
+        .add_from_transfac_reader(gzip(&read("test_files/transfac.txt")).as_slice())
+        .unwrap();
+    compressed
+        .add_from_profile_reader(gzip(&read("test_files/profile.txt")).as_slice())
+        .unwrap();
+    compressed
+        .add_from_fasta_reader(gzip(&read("test_files/fasta.txt")).as_slice())
+        .unwrap();
+
+    assert_eq!(plain.matrices.len(), compressed.matrices.len());
+    assert_eq!(plain.profiles, compressed.profiles);
+    assert_eq!(plain.sequences.len(), compressed.sequences.len());
+    assert_eq!(
+        plain.evaluate().unwrap().len(),
+        compressed.evaluate().unwrap().len()
+    );
+}
+
+#[test]
+fn multi_member_gzip() {
+    // BGZF is a concatenation of gzip members
+    let fasta = read("test_files/fasta.txt");
+    let (a, b) = fasta.split_at(fasta.len() / 2);
+    let mut bgzf = gzip(a);
+    bgzf.extend(gzip(b));
+
+    let mut calculator = TfpCalculator::default();
+    calculator.add_from_fasta_reader(bgzf.as_slice()).unwrap();
+    let mut reference = TfpCalculator::default();
+    reference
+        .add_from_fasta_file("test_files/fasta.txt")
+        .unwrap();
+
+    assert_eq!(calculator.sequences.len(), reference.sequences.len());
+    for (c, r) in calculator.sequences.iter().zip(reference.sequences.iter()) {
+        assert_eq!(c.name, r.name);
+        assert_eq!(c.seq, r.seq);
+    }
+}
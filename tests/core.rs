@@ -0,0 +1,106 @@
+use tfp::builder::{Tfp, TfpCalculator, TfpError};
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+const SHORT: &str = "ID Short
+XX
+P0 A C G T
+01 0 20 0 0
+02 20 0 0 0
+03 0 18 2 0
+04 0 0 20 0
+//
+";
+
+fn evaluate(core_length: usize, seq: &str) -> Vec<Tfp> {
+    let mut calculator = common::calculator_for(seq);
+    calculator
+        .add_from_transfac_reader(SHORT.as_bytes())
+        .unwrap();
+    calculator.set_core_length(core_length);
+    calculator.evaluate().unwrap()
+}
+
+fn assert_css_is_mss(hits: &[Tfp], matrix: &str) {
+    let hits: Vec<_> = hits.iter().filter(|t| t.matrix == matrix).collect();
+    assert!(!hits.is_empty());
+    for t in hits {
+        assert!((*t.css - *t.mss).abs() < 1e-5, "{t:?}");
+    }
+}
+
+#[test]
+fn short_matrix_uses_whole_matrix_as_core() {
+    let hits = evaluate(5, "TTCACGTTCAGGTT");
+    assert_css_is_mss(&hits, "Short");
+    let consensus = hits
+        .iter()
+        .find(|t| t.matrix == "Short" && t.strand && t.pos == 2)
+        .unwrap();
+    assert_eq!(consensus.site, "CACG");
+    assert_eq!(*consensus.css, 1.0);
+}
+
+#[test]
+fn core_length() {
+    let seq = "GATTCACGTGATTTAACATAATGAACGTGT";
+    // Test1 has 6 positions, Test2 is longer than the precomputed cores
+    assert_css_is_mss(&evaluate(6, seq), "Test1");
+    assert_css_is_mss(&evaluate(14, seq), "Test2");
+    assert_css_is_mss(&evaluate(100, seq), "Test2");
+
+    // The default core differs from the matrix, but scores the same windows
+    let default = evaluate(5, seq);
+    let whole = evaluate(6, seq);
+    assert_eq!(default.len(), whole.len());
+    assert!(default
+        .iter()
+        .zip(whole.iter())
+        .any(|(a, b)| a.matrix == "Test1" && a.css != b.css));
+}
+
+#[test]
+fn core_longer_than_words() {
+    // 40 positions cycling through the bases, so the whole matrix is the core
+    let mut matrix = String::from("ID Long\nXX\nP0 A C G T\n");
+    let mut seq = String::new();
+    for i in 0..40 {
+        let mut row = [1; 4];
+        row[i % 4] = 20;
+        matrix += &format!("{:02} {} {} {} {}\n", i + 1, row[0], row[1], row[2], row[3]);
+        seq.push(['A', 'C', 'G', 'T'][i % 4]);
+    }
+    matrix += "//\n";
+
+    let mut calculator = TfpCalculator::default();
+    calculator
+        .add_from_transfac_reader(matrix.as_bytes())
+        .unwrap();
+    calculator.add_sequence(PlusStrand::from_str("test", &format!("TT{seq}TT")).unwrap());
+    calculator.set_core_length(40);
+    let hits = calculator.evaluate().unwrap();
+    assert_css_is_mss(&hits, "Long");
+    let consensus = hits.iter().find(|t| t.strand && t.pos == 2).unwrap();
+    assert_eq!(*consensus.css, 1.0);
+}
+
+#[test]
+fn uniform_matrix_is_rejected() {
+    let mut calculator = common::calculator_for("GATTCACGTGATTTAACATAATGAACGTGT");
+    calculator
+        .add_from_transfac_reader("ID Uniform\nXX\nP0 A C G T\n01 5 5 5 5\n//\n".as_bytes())
+        .unwrap();
+    assert!(matches!(
+        calculator.compile(),
+        Err(TfpError::Uninformative(name)) if name == "Uniform"
+    ));
+    assert!(matches!(
+        calculator.clone().evaluate(),
+        Err(TfpError::Uninformative(_))
+    ));
+
+    // The other matrices are scanned once it is removed
+    calculator.matrices.retain(|m| m.name != "Uniform");
+    assert!(!calculator.evaluate().unwrap().is_empty());
+}
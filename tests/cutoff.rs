@@ -0,0 +1,107 @@
+use ordered_float::NotNan;
+use tfp::builder::{PseudocountPolicy, TfpCalculator};
+use tfp::cutoff::{generate_profile_sets, generate_profiles};
+use tfp::matrix::PwmMatrix;
+use tfp::parser::profile::{write_profiles, CutoffType, Profile, ProfileSet};
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+use common::{random_sequence, Lcg};
+
+fn test1() -> PwmMatrix {
+    common::calculator().matrices.remove(0)
+}
+
+fn sites() -> Vec<PlusStrand> {
+    ["CACGTG", "AACGTG", "TCACGTGT", "CACGTA", "GGCACGCG"]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| PlusStrand::from_str(&format!("site{i}"), s).unwrap())
+        .collect()
+}
+
+fn background() -> Vec<PlusStrand> {
+    let seq = random_sequence(&mut Lcg(42), 5000);
+    vec![PlusStrand::from_str("background", &seq).unwrap()]
+}
+
+fn hits(profile: &Profile, sequences: Vec<PlusStrand>) -> usize {
+    hits_with(TfpCalculator::default(), profile, sequences)
+}
+
+fn hits_with(mut c: TfpCalculator, profile: &Profile, sequences: Vec<PlusStrand>) -> usize {
+    c.add_pwm(test1());
+    c.add_profil(profile.clone());
+    sequences.into_iter().for_each(|s| c.add_sequence(s));
+    c.evaluate().unwrap().len()
+}
+
+#[test]
+fn cutoff_criteria() {
+    let [min_fn, min_fp, min_sum] =
+        generate_profiles(&test1(), &sites(), &background(), &TfpCalculator::default()).unwrap();
+    assert_eq!(min_fn.cutoff, CutoffType::MinFn);
+    assert_eq!(min_fp.cutoff, CutoffType::MinFp);
+    assert_eq!(min_sum.cutoff, CutoffType::MinSum);
+    assert_eq!(min_fn.id, "Test1");
+
+    // Every known site is found with minFN
+    for site in sites() {
+        assert!(hits(&min_fn, vec![site]) > 0);
+    }
+    let found = |p: &Profile| hits(p, background());
+    assert!(found(&min_fp) <= found(&min_sum));
+    assert!(found(&min_sum) <= found(&min_fn));
+    assert!(min_fp.css >= min_fn.css && min_fp.mss >= min_fn.mss);
+}
+
+#[test]
+fn no_scorable_site() {
+    let short = vec![PlusStrand::from_str("short", "CACG").unwrap()];
+    assert!(generate_profiles(&test1(), &short, &background(), &TfpCalculator::default()).is_err());
+}
+
+#[test]
+fn write_generated_sets() {
+    let pwm = test1();
+    let sites = sites();
+    let sets = generate_profile_sets(
+        [(&pwm, sites.as_slice())],
+        &background(),
+        &TfpCalculator::default(),
+    )
+    .unwrap();
+    let names: Vec<_> = sets.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["minFN", "minFP", "minSUM"]);
+
+    let mut out = Vec::new();
+    write_profiles(&mut out, &sets).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let (rest, parsed) = ProfileSet::parse_many(&out).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(parsed, sets);
+    let (_, profiles) = Profile::parse_many(&out).unwrap();
+    assert!(profiles.contains_key("Test1"));
+}
+
+#[test]
+fn cutoffs_use_calculator_settings() {
+    let mut settings = TfpCalculator::default();
+    settings.set_core_length(6);
+    settings.set_pseudocount_policy(PseudocountPolicy::Fixed);
+    settings.set_pseudocount(NotNan::new(4.0).unwrap());
+    let [default, ..] =
+        generate_profiles(&test1(), &sites(), &background(), &TfpCalculator::default()).unwrap();
+    let [min_fn, ..] = generate_profiles(&test1(), &sites(), &background(), &settings).unwrap();
+    assert_ne!((default.css, default.mss), (min_fn.css, min_fn.mss));
+
+    // The MSS cutoff is the score the calculator reports for the weakest site
+    for site in sites() {
+        assert!(hits_with(settings.clone(), &min_fn, vec![site]) > 0);
+    }
+    let mut c = settings.clone();
+    c.add_pwm(test1());
+    sites().into_iter().for_each(|s| c.add_sequence(s));
+    assert!(c.evaluate().unwrap().iter().any(|t| t.mss == min_fn.mss));
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use tfp::builder::{TfpCalculator, TfpError};
+use tfp::parser::error::{ParseWarning, RecordError};
+use tfp::parser::jaspar::JasparParseError;
+use tfp::parser::transfac::TransfacParseError;
+
+const TRANSFAC: &str = "ID a\nPO A C G T\n01 1 2 3 4\n//\n\
+                        ID b\nPO A C G T\n01 1 2 x 4\n//\n\
+                        ID c\nPO A C G T\n01 1 2 3 4\n03 1 2 3 4\n//\n\
+                        ID d\nPO A C G T\n01 1 2 3 4\n//\n";
+
+#[test]
+fn syntax_error_location() {
+    let mut c = TfpCalculator::default();
+    match c.add_from_transfac_reader(TRANSFAC.as_bytes()) {
+        Err(TfpError::Syntax { location, expected }) => {
+            assert_eq!(location.path, None);
+            assert_eq!(location.line, 7);
+            assert_eq!(location.column, 10);
+            assert_eq!(location.content, "01 1 2 x 4");
+            assert_eq!(expected, "position followed by numbers");
+        }
+        e => panic!("Unexpected result {e:?}"),
+    }
+    // Nothing is added in strict mode
+    assert!(c.matrices.is_empty());
+}
+
+#[test]
+fn lenient_mode_collects_warnings() {
+    let mut c = TfpCalculator::default();
+    c.set_lenient(true);
+    c.add_from_transfac_reader(TRANSFAC.as_bytes()).unwrap();
+    let names: Vec<_> = c.matrices.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "d"]);
+
+    assert_eq!(c.warnings.len(), 2);
+    assert!(matches!(&c.warnings[0], ParseWarning::Syntax { location, .. } if location.line == 7));
+    match &c.warnings[1] {
+        ParseWarning::InvalidRecord { location, error } => {
+            assert_eq!(location.line, 9);
+            assert_eq!(location.content, "ID c");
+            assert!(matches!(
+                error,
+                RecordError::Transfac(TransfacParseError::MissingRow(2))
+            ));
+        }
+        w => panic!("Unexpected warning {w:?}"),
+    }
+}
+
+#[test]
+fn record_error_with_path() {
+    let mut c = TfpCalculator::default();
+    let path = Path::new("test_files/meme.txt");
+    c.add_from_meme_file(path).unwrap();
+    assert_eq!(c.matrices.len(), 2);
+
+    let jaspar = ">a\nA [ 1 ]\nC [ 1 ]\nG [ 1 ]\nT [ 1 ]\n>b\nA [ 1 ]\nC [ 1 ]\n";
+    match c.add_from_jaspar_reader(jaspar.as_bytes()) {
+        Err(TfpError::InvalidRecord { location, error }) => {
+            assert_eq!(location.line, 6);
+            assert!(matches!(
+                error,
+                RecordError::Jaspar(JasparParseError::WrongRowCount(2))
+            ));
+        }
+        e => panic!("Unexpected result {e:?}"),
+    }
+}
+
+#[test]
+fn profile_error_message() {
+    let mut c = TfpCalculator::default();
+    let e = c
+        .add_from_profile_reader("1,0.88,0.88,M00001,Test1\n1,0.8x,0.81,M00004,Test2\n".as_bytes())
+        .unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Syntax error at 2:6 `1,0.8x,0.81,M00004,Test2`, expected profile entry \
+         '<type>,<css>,<mss>,<id>,<name>' (separator)"
+    );
+    assert!(c.profiles.is_empty());
+}
+
+const FASTA: &str = ">a\nACGT\nAC\n>b\nACGT\nACXGT\nACGT\n>c\nacgtn\n";
+
+#[test]
+fn fasta_error_location() {
+    let mut c = TfpCalculator::default();
+    let path = std::env::temp_dir().join("tfp_invalid.fa");
+    std::fs::write(&path, FASTA).unwrap();
+    match c.add_from_fasta_file(&path) {
+        Err(TfpError::Syntax { location, expected }) => {
+            assert_eq!(location.path, Some(path));
+            assert_eq!(location.line, 6);
+            assert_eq!(location.column, 3);
+            assert_eq!(location.content, "ACXGT");
+            assert_eq!(expected, "nucleotide");
+        }
+        e => panic!("Unexpected result {e:?}"),
+    }
+    assert!(c.sequences.is_empty());
+
+    // In lenient mode only the invalid record is skipped
+    c.set_lenient(true);
+    c.add_from_fasta_reader(FASTA.as_bytes()).unwrap();
+    let names: Vec<_> = c.sequences.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "c"]);
+    assert_eq!(c.warnings.len(), 1);
+    assert_eq!(c.warnings[0].location().line, 6);
+}
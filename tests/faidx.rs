@@ -0,0 +1,79 @@
+use std::io::{BufReader, Cursor};
+
+use tfp::parser::faidx::{FaiIndex, IndexedFasta, Region};
+
+mod common;
+
+const FASTA: &str = ">chr1 first\nACGTACGTAC\nGTACGTAC\n>chr2\nCACGTGtttt\nCACGTGAAAC\nAC\n";
+
+#[test]
+fn build_and_read_index() {
+    let index = FaiIndex::build(BufReader::new(FASTA.as_bytes())).unwrap();
+    let mut fai = Vec::new();
+    index.write(&mut fai).unwrap();
+    assert_eq!(
+        String::from_utf8(fai.clone()).unwrap(),
+        "chr1\t18\t12\t10\t11\nchr2\t22\t38\t10\t11\n"
+    );
+    assert_eq!(FaiIndex::read(fai.as_slice()).unwrap(), index);
+    for invalid in ["chr1\t18\t12\t0\t11\n", "chr1\t18\t12\t10\t9\n"] {
+        let error = FaiIndex::read(invalid.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+    // Empty sequences have no lines
+    assert!(FaiIndex::read("chr1\t0\t12\t0\t0\n".as_bytes()).is_ok());
+
+    let uneven = ">chr1\nACGT\nACGTACGT\n";
+    assert!(FaiIndex::build(BufReader::new(uneven.as_bytes())).is_err());
+}
+
+#[test]
+fn parse_regions() {
+    let region: Region = "chr1:1,001-2,000".parse().unwrap();
+    assert_eq!((region.start, region.end), (1000, Some(2000)));
+    assert_eq!(region.to_string(), "chr1:1001-2000");
+    assert_eq!("chr1".parse::<Region>().unwrap().end, None);
+    assert!("chr1:0-10".parse::<Region>().is_err());
+    assert!("chr1:20-10".parse::<Region>().is_err());
+}
+
+#[test]
+fn fetch_across_lines() {
+    let index = FaiIndex::build(BufReader::new(FASTA.as_bytes())).unwrap();
+    let mut fasta = IndexedFasta::new(Cursor::new(FASTA.as_bytes()), index);
+
+    let seq = fasta.fetch(&"chr2:5-17".parse().unwrap()).unwrap();
+    assert_eq!(seq.name, "chr2:5-17");
+    assert_eq!(seq.offset, 4);
+    let bases: String = seq.seq.iter().map(|b| b.to_string()).collect();
+    assert_eq!(bases, "TGTTTTCACGTGA");
+    assert_eq!(seq.mask, vec![2..6]);
+
+    let seq = fasta.fetch(&"chr1:15-100".parse().unwrap()).unwrap();
+    assert_eq!(seq.seq.len(), 4);
+}
+
+#[test]
+fn region_hits_in_genome_coordinates() {
+    let path = std::env::temp_dir().join("tfp_faidx_region_hits.fa");
+    std::fs::write(&path, FASTA).unwrap();
+
+    let mut region = common::calculator();
+    let mut whole = region.clone();
+    region
+        .add_regions_from_fasta_file(&path, &["chr2:9-20".parse().unwrap()])
+        .unwrap();
+    whole.add_from_fasta_file(&path).unwrap();
+
+    let region = region.evaluate().unwrap();
+    let whole = whole.evaluate().unwrap();
+    assert!(!region.is_empty());
+    for hit in region {
+        assert!(hit.pos >= 8);
+        assert!(whole.iter().any(|w| w.sequence == "chr2"
+            && w.pos == hit.pos
+            && w.strand == hit.strand
+            && w.mss == hit.mss));
+    }
+    std::fs::remove_file(path).unwrap();
+}
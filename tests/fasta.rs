@@ -0,0 +1,49 @@
+use std::io::BufReader;
+
+use tfp::builder::TfpCalculator;
+use tfp::parser::fasta::FastaReader;
+
+#[test]
+fn read_records() {
+    let c = std::fs::read_to_string("test_files/fasta.txt").unwrap();
+    let streamed = FastaReader::new(BufReader::new(c.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let names: Vec<_> = c.lines().filter_map(|l| l.strip_prefix('>')).collect();
+    assert_eq!(streamed.iter().map(|f| &f.name).collect::<Vec<_>>(), names);
+    let bases = c
+        .lines()
+        .filter(|l| !l.starts_with('>'))
+        .flat_map(|l| l.trim().chars());
+    assert!(streamed.iter().flat_map(|f| f.seq.chars()).eq(bases));
+}
+
+#[test]
+fn reader_rejects_sequence_without_header() {
+    let mut reader = FastaReader::new(BufReader::new("ACGT\n>a\nACGT\n".as_bytes()));
+    assert!(reader.next().unwrap().is_err());
+}
+
+#[test]
+fn streaming_evaluation() {
+    let mut calculator = TfpCalculator::default();
+    calculator
+        .add_from_transfac_file("test_files/transfac.txt")
+        .unwrap();
+    let streamed = calculator
+        .evaluate_fasta_file("test_files/fasta.txt")
+        .unwrap();
+
+    calculator
+        .add_from_fasta_file("test_files/fasta.txt")
+        .unwrap();
+    let evaluated = calculator.evaluate().unwrap();
+
+    assert_eq!(streamed.len(), evaluated.len());
+    for (s, e) in streamed.iter().zip(evaluated.iter()) {
+        assert_eq!(
+            (&s.sequence, &s.matrix, s.pos, s.strand, s.mss),
+            (&e.sequence, &e.matrix, e.pos, e.strand, e.mss)
+        );
+    }
+}
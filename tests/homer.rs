@@ -0,0 +1,82 @@
+use tfp::builder::{TfpCalculator, TfpError};
+use tfp::matrix::PwmMatrix;
+use tfp::parser::error::RecordError;
+use tfp::parser::homer::HomerParseError;
+use tfp::parser::homer::{parse_homer, write_homer};
+use tfp::sequence::PlusStrand;
+
+fn parse(c: &str) -> Vec<PwmMatrix> {
+    let (rest, groups) = parse_homer(c).unwrap();
+    assert!(rest.is_empty());
+    groups
+        .into_iter()
+        .map(|g| PwmMatrix::try_from(g).unwrap())
+        .collect()
+}
+
+#[test]
+fn parse_homer_file() {
+    let matrices = parse(&std::fs::read_to_string("test_files/homer.txt").unwrap());
+    assert_eq!(matrices.len(), 2);
+    assert_eq!(matrices[0].name, "MyoD(bHLH)/Test");
+    assert_eq!(matrices[0].metadata.log_odds_threshold, Some(5.5));
+    assert_eq!(matrices[0].matrix.nrows(), 6);
+    assert_eq!(*matrices[0].matrix[(1, 0)], 0.997);
+    assert_eq!(matrices[1].metadata.log_odds_threshold, Some(4.0));
+
+    let (_, groups) = parse_homer(">A\tno_threshold\n0.25 0.25 0.25 0.25\n").unwrap();
+    assert!(PwmMatrix::try_from(groups[0].clone()).is_err());
+}
+
+#[test]
+fn threshold_is_cutoff() {
+    let mut c = TfpCalculator::default();
+    c.add_from_homer_file("test_files/homer.txt").unwrap();
+    c.matrices.truncate(1);
+    c.add_sequence(PlusStrand::from_str("hit", "TTCAGCTGTT").unwrap());
+    c.add_sequence(PlusStrand::from_str("miss", "TTCAGGTGTT").unwrap());
+
+    let hits = c.evaluate().unwrap();
+    // The site is palindromic and found on both strands
+    assert_eq!(hits.len(), 2);
+    assert!(hits
+        .iter()
+        .all(|h| h.sequence == "hit" && h.pos == 2 && h.site == "CAGCTG"));
+}
+
+#[test]
+fn write_and_parse_again() {
+    let c = std::fs::read_to_string("test_files/homer.txt").unwrap();
+    let matrices = parse(&c);
+    let mut out = Vec::new();
+    write_homer(&mut out, &matrices).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with(">CAGCTG\tMyoD(bHLH)/Test\t5.5\n"));
+    assert!(out.contains(">GATCA\tSecond\t4\n"));
+
+    let written = parse(&out);
+    assert_eq!(matrices, written);
+}
+
+#[test]
+fn empty_header_fields() {
+    let c = ">CAGCTG\t\t5.5\n0.1\t0.7\t0.1\t0.1\n0.9\t0.03\t0.03\t0.04\n\
+             >\tSecond\t4.0\t\t0\n0.1\t0.1\t0.7\t0.1\n";
+    let matrices = parse(c);
+    let names: Vec<_> = matrices.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["CAGCTG", "Second"]);
+    assert_eq!(matrices[0].matrix.nrows(), 2);
+    assert_eq!(matrices[1].matrix.nrows(), 1);
+    assert_eq!(matrices[1].metadata.log_odds_threshold, Some(4.0));
+
+    // A header without consensus and name is an invalid record, not part of the previous one
+    let mut calculator = TfpCalculator::default();
+    let c = format!("{c}>\t\t4.0\n0.1\t0.1\t0.7\t0.1\n");
+    match calculator.add_from_homer_reader(c.as_bytes()) {
+        Err(TfpError::InvalidRecord { location, error }) => {
+            assert_eq!(location.line, 6);
+            assert!(matches!(error, RecordError::Homer(HomerParseError::NoName)));
+        }
+        e => panic!("Unexpected result {e:?}"),
+    }
+}
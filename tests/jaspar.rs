@@ -0,0 +1,88 @@
+use tfp::builder::TfpCalculator;
+use tfp::matrix::PwmMatrix;
+use tfp::parser::jaspar::{parse_jaspar, write_jaspar, write_pfm};
+
+fn parse(c: &str) -> Vec<PwmMatrix> {
+    let (rest, groups) = parse_jaspar(c).unwrap();
+    assert!(rest.is_empty());
+    groups
+        .into_iter()
+        .map(|g| PwmMatrix::try_from(g).unwrap())
+        .collect()
+}
+
+#[test]
+fn jaspar_matches_transfac() {
+    let mut jaspar = TfpCalculator::default();
+    jaspar
+        .add_from_jaspar_file("test_files/jaspar.txt")
+        .unwrap();
+    let mut transfac = TfpCalculator::default();
+    transfac
+        .add_from_transfac_file("test_files/transfac.txt")
+        .unwrap();
+
+    assert_eq!(jaspar.matrices.len(), 2);
+    let arnt = &jaspar.matrices[0];
+    assert_eq!(arnt.name, "MA0004.1");
+    assert_eq!(arnt.metadata.factor.as_deref(), Some("Arnt"));
+    assert_eq!(arnt.matrix.nrows(), transfac.matrices[0].matrix.nrows());
+    // Only the second row differs (19.5 A in the TRANSFAC test file)
+    for i in [0, 2, 3, 4, 5] {
+        assert_eq!(arnt.matrix.row(i), transfac.matrices[0].matrix.row(i));
+    }
+    assert_eq!(
+        jaspar.matrices[1].metadata.factor.as_deref(),
+        Some("Ahr::Arnt")
+    );
+}
+
+#[test]
+fn bare_pfm() {
+    let pfm = "4 19 0 0 0 0\n16 0 20 0 0 0\n0 1 0 20 0 20\n0 0 0 0 20 0\n";
+    let mut calculator = TfpCalculator::default();
+    calculator
+        .add_from_pfm_reader(pfm.as_bytes(), "MA0004.1")
+        .unwrap();
+    calculator
+        .add_from_jaspar_file("test_files/jaspar.txt")
+        .unwrap();
+    assert_eq!(calculator.matrices[0].name, "MA0004.1");
+    assert_eq!(calculator.matrices[0].matrix, calculator.matrices[1].matrix);
+
+    let mut written = Vec::new();
+    write_pfm(&mut written, &calculator.matrices[0]).unwrap();
+    let mut again = TfpCalculator::default();
+    again.add_from_pfm_reader(written.as_slice(), "x").unwrap();
+    assert_eq!(again.matrices[0].matrix, calculator.matrices[0].matrix);
+}
+
+#[test]
+fn reject_malformed_matrices() {
+    let missing = ">a\nA [ 1 2 ]\nC [ 1 2 ]\nG [ 1 2 ]\n";
+    let (_, groups) = parse_jaspar(missing).unwrap();
+    assert!(PwmMatrix::try_from(groups[0].clone()).is_err());
+
+    let uneven = ">a\nA [ 1 2 ]\nC [ 1 2 ]\nG [ 1 2 ]\nT [ 1 ]\n";
+    let (_, groups) = parse_jaspar(uneven).unwrap();
+    assert!(PwmMatrix::try_from(groups[0].clone()).is_err());
+
+    let duplicate = ">a\nA [ 1 ]\nA [ 1 ]\nG [ 1 ]\nT [ 1 ]\n";
+    let (_, groups) = parse_jaspar(duplicate).unwrap();
+    assert!(PwmMatrix::try_from(groups[0].clone()).is_err());
+}
+
+#[test]
+fn write_and_parse_again() {
+    let matrices = parse(&std::fs::read_to_string("test_files/jaspar.txt").unwrap());
+    let mut out = Vec::new();
+    write_jaspar(&mut out, &matrices).unwrap();
+    let written = parse(&String::from_utf8(out).unwrap());
+
+    assert_eq!(matrices.len(), written.len());
+    for (a, b) in matrices.iter().zip(written.iter()) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.metadata, b.metadata);
+        assert_eq!(a.matrix, b.matrix);
+    }
+}
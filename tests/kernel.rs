@@ -0,0 +1,48 @@
+use tfp::builder::{AmbiguityPolicy, TfpCalculator};
+use tfp::matrix::{ExtendedTfpMatrix, TfpMatrix};
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+use common::{random_genomic_sequence, Lcg};
+
+fn matrices() -> Vec<ExtendedTfpMatrix> {
+    let mut c = TfpCalculator::default();
+    c.add_from_transfac_file("test_files/transfac.txt").unwrap();
+    c.add_from_meme_file("test_files/meme.txt").unwrap();
+    c.add_from_jaspar_file("test_files/jaspar.txt").unwrap();
+    c.matrices
+        .iter()
+        .map(|m| ExtendedTfpMatrix::try_from(TfpMatrix::from(m)).unwrap())
+        .collect()
+}
+
+#[test]
+fn kernel_matches_window_scoring() {
+    let policies = [
+        AmbiguityPolicy::Skip,
+        AmbiguityPolicy::Min,
+        AmbiguityPolicy::Average,
+        AmbiguityPolicy::Max,
+    ];
+    // Shorter than a matrix, within one block and across several blocks
+    let mut rng = Lcg(7);
+    for len in [3, 40, 1000] {
+        let seq = random_genomic_sequence(&mut rng, len);
+        let seq = PlusStrand::from_str("test", &seq).unwrap().seq;
+        for m in matrices() {
+            for policy in policies {
+                let expected: Vec<_> = seq
+                    .windows(m.width())
+                    .map(|w| m.mss(w, policy).map(|s| s.to_bits()))
+                    .collect();
+                let scores: Vec<_> = m
+                    .mss_scores(&seq, policy)
+                    .into_iter()
+                    .map(|s| s.map(|s| s.to_bits()))
+                    .collect();
+                assert_eq!(scores, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use tfp::builder::{MaskPolicy, Tfp};
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+fn evaluate(seq: &str, policy: MaskPolicy) -> Vec<Tfp> {
+    let mut calculator = common::calculator_for(seq);
+    calculator.set_mask_policy(policy);
+    calculator
+        .evaluate()
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.matrix == "Test1")
+        .collect()
+}
+
+#[test]
+fn lowercase_is_stored_as_mask() {
+    let seq = PlusStrand::from_str("test", "acGTacgT").unwrap();
+    assert_eq!(seq.mask, vec![0..2, 4..7]);
+    assert_eq!(
+        seq.seq,
+        PlusStrand::from_str("test", "ACGTACGT").unwrap().seq
+    );
+}
+
+#[test]
+fn mask_policies() {
+    // Only windows starting at 0..=3 end before the masked tail at 9
+    let seq = "CACGTGTTTttttt";
+    let count = evaluate(seq, MaskPolicy::Count);
+    let ignore = evaluate(seq, MaskPolicy::Ignore);
+    let unmask = evaluate(seq, MaskPolicy::Unmask);
+
+    assert_eq!(count.len(), unmask.len());
+    assert!(count.iter().all(|t| t.masked == (t.pos > 3)));
+    assert!(unmask.iter().all(|t| t.masked == (t.pos > 3)));
+    assert!(ignore.iter().all(|t| !t.masked && t.pos <= 3));
+    assert_eq!(ignore.len(), count.iter().filter(|t| !t.masked).count());
+    assert!(count
+        .iter()
+        .all(|t| t.site.chars().all(|c| c.is_ascii_uppercase())));
+}
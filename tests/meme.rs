@@ -0,0 +1,93 @@
+use tfp::background::Background;
+use tfp::builder::TfpCalculator;
+use tfp::matrix::PwmMatrix;
+use tfp::parser::meme::{parse_meme, write_meme, MemeFile};
+
+fn parse(c: &str) -> MemeFile {
+    let (rest, tags) = parse_meme(c).unwrap();
+    assert!(rest.is_empty());
+    MemeFile::try_from(tags).unwrap()
+}
+
+fn assert_close(a: &PwmMatrix, b: &PwmMatrix) {
+    assert_eq!(a.name, b.name);
+    assert_eq!(a.metadata, b.metadata);
+    assert_eq!(a.matrix.nrows(), b.matrix.nrows());
+    for (a, b) in a.matrix.iter().zip(b.matrix.iter()) {
+        assert!((**a - **b).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn parse_meme_file() {
+    let meme = parse(&std::fs::read_to_string("test_files/meme.txt").unwrap());
+    assert_eq!(meme.version.as_deref(), Some("4"));
+    assert_eq!(
+        meme.background.map(|b| b.map(f32::from)),
+        Some([0.3, 0.2, 0.2, 0.3])
+    );
+    assert_eq!(meme.matrices.len(), 2);
+
+    let arnt = &meme.matrices[0];
+    assert_eq!(arnt.name, "MA0004.1");
+    assert_eq!(arnt.metadata.factor.as_deref(), Some("Arnt"));
+    assert_eq!(arnt.metadata.nsites, Some(20.0));
+    assert_eq!(
+        arnt.metadata.url.as_deref(),
+        Some("http://jaspar.genereg.net/matrix/MA0004.1")
+    );
+    assert_eq!(arnt.matrix.nrows(), 6);
+    assert_eq!(*arnt.matrix[(1, 0)], 0.95);
+
+    // The log-odds matrix in front of the probabilities is skipped
+    let ahr = &meme.matrices[1];
+    assert_eq!(ahr.metadata.factor.as_deref(), Some("Ahr::Arnt"));
+    assert_eq!(ahr.metadata.e_value, Some(1.2e-10));
+    assert_eq!(*ahr.matrix[(0, 3)], 0.458333);
+}
+
+#[test]
+fn reject_malformed_motifs() {
+    let wrong_width = "MOTIF a\nletter-probability matrix: alength= 4 w= 3\n0.25 0.25 0.25 0.25\n";
+    let (_, tags) = parse_meme(wrong_width).unwrap();
+    assert!(MemeFile::try_from(tags).is_err());
+
+    let protein = "ALPHABET= ACDEFGHIKLMNPQRSTVWY\n";
+    let (_, tags) = parse_meme(protein).unwrap();
+    assert!(MemeFile::try_from(tags).is_err());
+}
+
+#[test]
+fn write_and_parse_again() {
+    let meme = parse(&std::fs::read_to_string("test_files/meme.txt").unwrap());
+    let mut out = Vec::new();
+    write_meme(&mut out, &meme.matrices, meme.background).unwrap();
+
+    let written = parse(&String::from_utf8(out).unwrap());
+    assert_eq!(written.background, meme.background);
+    assert_eq!(written.matrices.len(), meme.matrices.len());
+    for (a, b) in meme.matrices.iter().zip(written.matrices.iter()) {
+        assert_close(a, b);
+    }
+}
+
+#[test]
+fn background_of_motif_file() {
+    let mut calculator = TfpCalculator::default();
+    calculator
+        .add_from_meme_file("test_files/meme.txt")
+        .unwrap();
+    assert_eq!(calculator.get_background(), &Background::default());
+
+    calculator.set_meme_background(true);
+    calculator
+        .add_from_meme_file("test_files/meme.txt")
+        .unwrap();
+    let frequencies = calculator.get_background().frequencies();
+    for (f, expected) in frequencies.iter().zip([0.3, 0.2, 0.2, 0.3]) {
+        assert!((f - expected).abs() < 1e-6);
+    }
+
+    let zero = "MEME version 4\n\nBackground letter frequencies\nA 0.5 C 0.5 G 0 T 0\n";
+    assert!(calculator.add_from_meme_reader(zero.as_bytes()).is_err());
+}
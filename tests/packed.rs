@@ -0,0 +1,46 @@
+use tfp::sequence::{Base, PackedSequence, PlusStrand};
+
+fn packed(seq: &str) -> (PlusStrand, PackedSequence) {
+    let seq = PlusStrand::from_str("test", seq).unwrap();
+    let packed = PackedSequence::from(seq.seq.as_slice());
+    (seq, packed)
+}
+
+#[test]
+fn bases() {
+    // Longer than one word of packed bases
+    let s = "ACGTNACGTRACGTACGTACGTACGTACGTACGTACGTAC";
+    let (seq, packed) = packed(s);
+    assert_eq!(packed.len(), s.len());
+    for (i, n) in seq.seq.iter().enumerate() {
+        assert_eq!(packed.base(i), n.as_base());
+        assert_eq!(packed.is_ambiguous(i), n.is_ambiguous());
+    }
+}
+
+#[test]
+fn kmers() {
+    let s = "GATTACANNCAGCTGTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTACGTW";
+    let (seq, packed) = packed(s);
+    for k in [1, 3, 5, 7, 32] {
+        let expected: Vec<_> = seq
+            .seq
+            .windows(k)
+            .map(|w| {
+                w.iter()
+                    .try_fold(0, |i, n| Some(i * 4 + n.as_base()? as usize))
+            })
+            .collect();
+        assert_eq!(packed.kmers(k).collect::<Vec<_>>(), expected, "k = {k}");
+    }
+    assert_eq!(packed.kmers(3).next(), Some(Some(0b10_00_11)));
+    assert_eq!(Base::from_code(0b10), Base::G);
+}
+
+#[test]
+fn shorter_than_word() {
+    let (_, packed) = packed("ACG");
+    assert_eq!(packed.kmers(5).count(), 0);
+    assert_eq!(packed.kmers(3).count(), 1);
+    assert_eq!(PackedSequence::default().kmers(1).count(), 0);
+}
@@ -0,0 +1,94 @@
+use tfp::builder::{TfpCalculator, TfpError};
+use tfp::matrix::Float;
+use tfp::parser::profile::{write_profiles, CutoffType, Profile, ProfileSet};
+
+const PROFILES: &str = "# Generated for tests\n\
+                        vertebrates_minFN\n\
+                        cutoffs minimising false negatives\n\
+                        MIN_LENGTH 300\n\
+                        0.0\n\
+                        1,0.88,0.88,M00001,Test1\n\
+                        1 0.82 0.81 M00004 Test2\n\
+                        //\n\
+                        vertebrates_minFP\n\
+                        2, 0.95, 0.9, M00001, Test1\n\
+                        3,1.0,0.85,M00004\n\
+                        //\n";
+
+fn parse(c: &str) -> Vec<ProfileSet> {
+    let (rest, sets) = ProfileSet::parse_many(c).unwrap();
+    assert!(rest.is_empty());
+    sets
+}
+
+#[test]
+fn parse_sections() {
+    let sets = parse(PROFILES);
+    assert_eq!(sets.len(), 2);
+    assert_eq!(sets[0].name, "vertebrates_minFN");
+    assert_eq!(
+        sets[0].description.as_deref(),
+        Some("cutoffs minimising false negatives")
+    );
+    assert_eq!(sets[0].min_length, Some(300));
+    assert_eq!(
+        sets[0].get("M00004"),
+        Some(&Profile {
+            cutoff: CutoffType::MinFn,
+            css: Float::new(0.82).unwrap(),
+            mss: Float::new(0.81).unwrap(),
+            id: "M00004".to_string(),
+            factor: Some("Test2".to_string()),
+        })
+    );
+    assert_eq!(sets[1].name, "vertebrates_minFP");
+    assert_eq!(sets[1].profiles[0].cutoff, CutoffType::MinFp);
+    assert_eq!(sets[1].profiles[1].cutoff, CutoffType::MinSum);
+    assert_eq!(sets[1].profiles[1].factor, None);
+
+    // Plain entry lists are a single unnamed set
+    let plain = parse(&std::fs::read_to_string("test_files/profile.txt").unwrap());
+    assert_eq!(plain.len(), 1);
+    assert_eq!(plain[0].name, "");
+    assert_eq!(plain[0].profiles.len(), 2);
+}
+
+#[test]
+fn select_set_by_name() {
+    let mut c = TfpCalculator::default();
+    c.add_from_profile_reader(PROFILES.as_bytes()).unwrap();
+    assert_eq!(c.profile_sets.len(), 2);
+    // Later sections override earlier ones until a set is selected
+    assert_eq!(*c.profiles["M00001"].0, 0.95);
+
+    c.use_profile_set("vertebrates_minFN").unwrap();
+    assert_eq!(*c.profiles["M00001"].0, 0.88);
+    assert!(matches!(
+        c.use_profile_set("unknown"),
+        Err(TfpError::UnknownProfileSet(_))
+    ));
+}
+
+#[test]
+fn write_and_parse_again() {
+    let sets = parse(PROFILES);
+    let mut out = Vec::new();
+    write_profiles(&mut out, &sets).unwrap();
+    assert_eq!(parse(&String::from_utf8(out).unwrap()), sets);
+}
+
+#[test]
+fn header_text_starting_with_digits() {
+    let c = "vertebrates\n2009 release\n1,0.88,0.88,M00001,Test1\n//\n";
+    let sets = parse(c);
+    assert_eq!(sets[0].description.as_deref(), Some("2009 release"));
+    assert_eq!(sets[0].profiles.len(), 1);
+
+    // Comma separated lines are still required to be entries
+    let mut calculator = TfpCalculator::default();
+    let malformed = "vertebrates\n1,0.88,M00001\n//\n";
+    assert!(matches!(
+        calculator.add_from_profile_reader(malformed.as_bytes()),
+        Err(TfpError::Syntax { .. })
+    ));
+}
@@ -0,0 +1,162 @@
+use ordered_float::NotNan;
+use tfp::builder::{PseudocountPolicy, Tfp, TfpCalculator, TfpResult};
+use tfp::matrix::MatrixKind;
+use tfp::sequence::PlusStrand;
+
+const SEQUENCE: &str = "TTCACGTGAACAGCTGCCACGTATGCACGCGTTAACGTG";
+
+/// Rows of Test1 of `test_files/transfac.txt` scaled to `total`
+fn scaled_rows(total: f32) -> Vec<[f32; 4]> {
+    let rows = [
+        [4.0, 16.0, 0.0, 0.0],
+        [19.5, 0.0, 1.0, 0.0],
+        [0.0, 20.0, 0.0, 0.0],
+        [0.0, 0.0, 20.0, 0.0],
+        [0.0, 0.0, 0.0, 20.0],
+        [0.0, 0.0, 20.0, 0.0],
+    ];
+    rows.iter()
+        .map(|r| {
+            let sum: f32 = r.iter().sum();
+            r.map(|v| v / sum * total)
+        })
+        .collect()
+}
+
+/// Test1 as TRANSFAC record with every row scaled to `total`
+fn scaled_test1(name: &str, total: f32) -> String {
+    let mut c = format!("ID {name}\nXX\nP0 A C G T\n");
+    for (i, r) in scaled_rows(total).iter().enumerate() {
+        c += &format!("{:02} {} {} {} {}\n", i + 1, r[0], r[1], r[2], r[3]);
+    }
+    c + "//\n"
+}
+
+/// Test1 as plain matrix with one line per base, which does not declare its kind
+fn scaled_test1_pfm(total: f32) -> String {
+    let rows = scaled_rows(total);
+    ["A", "C", "G", "T"]
+        .iter()
+        .enumerate()
+        .map(|(b, base)| {
+            let values: Vec<_> = rows.iter().map(|r| r[b].to_string()).collect();
+            format!("{base} [ {} ]\n", values.join(" "))
+        })
+        .collect()
+}
+
+fn evaluate(calculator: &TfpCalculator) -> Vec<Tfp> {
+    let mut calculator = calculator.clone();
+    calculator.add_sequence(PlusStrand::from_str("test", SEQUENCE).unwrap());
+    calculator.set_default_css_threshold(NotNan::new(0.0).unwrap());
+    calculator.set_default_mss_threshold(NotNan::new(0.0).unwrap());
+    let mut hits = calculator.evaluate().unwrap();
+    hits.sort_by_key(|t| (t.matrix.clone(), t.pos, t.strand));
+    hits
+}
+
+/// MSS of the hits of both matrices, which have to be found at the same places
+fn mss(hits: &[Tfp], a: &str, b: &str) -> (Vec<f32>, Vec<f32>) {
+    let of = |m: &str| {
+        hits.iter()
+            .filter(|t| t.matrix == m)
+            .map(|t| *t.mss)
+            .collect()
+    };
+    (of(a), of(b))
+}
+
+#[test]
+fn detect_kind() {
+    let kinds = |load: fn(&mut TfpCalculator) -> TfpResult<()>| {
+        let mut c = TfpCalculator::default();
+        load(&mut c).unwrap();
+        c.matrices.iter().map(|m| m.kind).collect::<Vec<_>>()
+    };
+    let counts = [MatrixKind::Counts; 2];
+    let frequencies = [MatrixKind::Frequencies; 2];
+    assert_eq!(
+        kinds(|c| c.add_from_transfac_file("test_files/transfac.txt")),
+        counts
+    );
+    assert_eq!(
+        kinds(|c| c.add_from_jaspar_file("test_files/jaspar.txt")),
+        counts
+    );
+    assert_eq!(
+        kinds(|c| c.add_from_meme_file("test_files/meme.txt")),
+        frequencies
+    );
+    assert_eq!(
+        kinds(|c| c.add_from_homer_file("test_files/homer.txt")),
+        frequencies
+    );
+    // Declared by the format, even if the rows sum to one
+    assert_eq!(
+        kinds(|c| c.add_from_transfac_reader(scaled_test1("One site", 1.0).as_bytes())),
+        [MatrixKind::Counts]
+    );
+    // Detected for plain matrices
+    assert_eq!(
+        kinds(|c| c.add_from_pfm_reader(scaled_test1_pfm(1.0).as_bytes(), "Frequencies")),
+        [MatrixKind::Frequencies]
+    );
+    assert_eq!(
+        kinds(|c| c.add_from_pfm_reader(scaled_test1_pfm(20.0).as_bytes(), "Counts")),
+        [MatrixKind::Counts]
+    );
+}
+
+#[test]
+fn frequencies_count_as_sites() {
+    let mut c = TfpCalculator::default();
+    c.add_from_pfm_reader(scaled_test1_pfm(1.0).as_bytes(), "Frequencies")
+        .unwrap();
+    c.add_from_transfac_reader(scaled_test1("Counts", 100.0).as_bytes())
+        .unwrap();
+
+    // The legacy conversion treats both as counts
+    let (frequencies, counts) = mss(&evaluate(&c), "Frequencies", "Counts");
+    assert_eq!(frequencies.len(), counts.len());
+    assert_ne!(frequencies, counts);
+
+    // Frequencies without a number of sites are taken as 100 sites
+    c.set_pseudocount_policy(PseudocountPolicy::Fixed);
+    let (frequencies, counts) = mss(&evaluate(&c), "Frequencies", "Counts");
+    assert!(!frequencies.is_empty());
+    for (f, c) in frequencies.iter().zip(counts.iter()) {
+        assert!((f - c).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn pseudocount_policies() {
+    let mut c = TfpCalculator::default();
+    c.add_from_transfac_file("test_files/transfac.txt").unwrap();
+    c.set_pseudocount_policy(PseudocountPolicy::Fixed);
+    let fixed = evaluate(&c);
+
+    // The background policy spreads the pseudocount like the fixed one for a uniform background
+    c.set_pseudocount_policy(PseudocountPolicy::Background);
+    let background = evaluate(&c);
+    assert_eq!(fixed.len(), background.len());
+    assert!(fixed
+        .iter()
+        .zip(background.iter())
+        .all(|(a, b)| a.mss == b.mss));
+
+    // A stronger pseudocount makes mismatches less costly
+    c.set_pseudocount_policy(PseudocountPolicy::Fixed);
+    c.set_pseudocount(NotNan::new(10.0).unwrap());
+    let strong = evaluate(&c);
+    let mismatch = |hits: &[Tfp]| {
+        hits.iter()
+            .find(|t| t.matrix == "Test1" && t.site == "CACGTA")
+            .map(|t| *t.mss)
+            .unwrap()
+    };
+    assert!(mismatch(&strong) > mismatch(&fixed));
+
+    c.set_pseudocount_policy(PseudocountPolicy::SqrtN);
+    assert_eq!(evaluate(&c).len(), fixed.len());
+}
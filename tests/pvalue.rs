@@ -0,0 +1,105 @@
+use tfp::background::Background;
+use tfp::builder::Tfp;
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+use common::{calculator_for, random_sequence, Lcg};
+
+fn evaluate(seq: &str, p_value: Option<f64>) -> Vec<Tfp> {
+    let mut calculator = calculator_for(seq);
+    calculator.set_p_value_threshold(p_value);
+    calculator.evaluate().unwrap()
+}
+
+#[test]
+fn consensus_p_value() {
+    // Every position of Test1 has a single best base
+    let hit = evaluate("CACGTG", None)
+        .into_iter()
+        .find(|t| t.matrix == "Test1" && t.strand && t.pos == 0)
+        .unwrap();
+    assert!((hit.p_value - 0.25f64.powi(6)).abs() < 1e-12);
+}
+
+#[test]
+fn p_values_follow_mss() {
+    let seq = random_sequence(&mut Lcg(42), 2000);
+    let hits: Vec<_> = evaluate(&seq, None)
+        .into_iter()
+        .filter(|t| t.matrix == "Test2")
+        .collect();
+    assert!(hits.iter().all(|t| t.p_value > 0.0 && t.p_value <= 1.0));
+    // Discretisation may reorder windows with almost equal scores
+    for a in hits.iter() {
+        for b in hits.iter().filter(|b| *b.mss > *a.mss + 0.002) {
+            assert!(b.p_value <= a.p_value, "{a:?} {b:?}");
+        }
+    }
+}
+
+#[test]
+fn p_value_threshold() {
+    let seq = random_sequence(&mut Lcg(42), 5000);
+    let cutoff = 1e-3;
+    let key = |t: &Tfp| (t.matrix.clone(), t.pos, t.strand);
+    let mut expected: Vec<_> = evaluate(&seq, None)
+        .iter()
+        .filter(|t| t.p_value <= cutoff)
+        .map(key)
+        .collect();
+    let hits = evaluate(&seq, Some(cutoff));
+    let mut found: Vec<_> = hits.iter().map(key).collect();
+    expected.sort();
+    found.sort();
+    assert!(!found.is_empty());
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn p_values_match_enumeration() {
+    // Test1 has 6 positions, all 4^6 windows are scored
+    let frequencies = [0.3, 0.2, 0.2, 0.3];
+    let mut calculator = common::calculator();
+    calculator.matrices.truncate(1);
+    calculator.set_background(Background::from_frequencies(frequencies).unwrap());
+    for i in 0..4096usize {
+        let window: String = (0..6)
+            .map(|j| ['A', 'C', 'G', 'T'][i >> (2 * j) & 3])
+            .collect();
+        calculator.add_sequence(PlusStrand::from_str(&window, &window).unwrap());
+    }
+    let windows: Vec<_> = calculator
+        .evaluate()
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.strand)
+        .map(|t| {
+            let mass: f64 = t
+                .site
+                .chars()
+                .map(|b| frequencies["ACGT".find(b).unwrap()])
+                .product();
+            (*t.mss as f64, t.p_value, mass)
+        })
+        .collect();
+    assert_eq!(windows.len(), 4096);
+
+    // Background mass of the windows scoring at least `mss`
+    let mass_from = |mss: f64| -> f64 {
+        windows
+            .iter()
+            .filter(|(m, _, _)| *m >= mss)
+            .map(|(_, _, mass)| mass)
+            .sum()
+    };
+    // Each position is rounded to 1 / 10000 of the MSS range
+    let step = 6.0 / 10_000.0;
+    for (mss, p_value, _) in windows.iter() {
+        let (low, high) = (mass_from(mss + step), mass_from(mss - step));
+        assert!(
+            low - 1e-9 <= *p_value && *p_value <= high + 1e-9,
+            "{mss} {p_value} {low} {high}"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use ordered_float::NotNan;
+use tfp::builder::TfpCalculator;
+use tfp::sequence::PlusStrand;
+
+mod common;
+
+use common::summary;
+
+fn sequences() -> Vec<PlusStrand> {
+    [
+        ("a", "TTCACGTGAACAGCTGCCACGTATGCACGCGTTAACGTG"),
+        ("b", "GATTCACGTGATTTAACATAATGAACGTGT"),
+        ("c", "CAC"),
+    ]
+    .map(|(name, seq)| PlusStrand::from_str(name, seq).unwrap())
+    .into()
+}
+
+fn calculator() -> TfpCalculator {
+    let mut calculator = common::calculator();
+    calculator.set_default_css_threshold(NotNan::new(0.5).unwrap());
+    calculator.set_default_mss_threshold(NotNan::new(0.5).unwrap());
+    calculator
+}
+
+#[test]
+fn scanner_matches_evaluate() {
+    let mut calculator = calculator();
+    let scanner = calculator.compile().unwrap();
+    assert_eq!(scanner.matrix_names(), ["Test1", "Test2"]);
+    for seq in sequences() {
+        calculator.add_sequence(seq);
+    }
+    let expected = summary(&calculator.evaluate().unwrap());
+    assert!(!expected.is_empty());
+
+    let seqs = sequences();
+    assert_eq!(summary(&scanner.scan_many(&seqs)), expected);
+    assert_eq!(summary(&scanner.scan_many(sequences())), expected);
+    let one_by_one: Vec<_> = seqs.iter().flat_map(|s| scanner.scan(s)).collect();
+    assert_eq!(summary(&one_by_one), expected);
+}
+
+#[test]
+fn scanner_keeps_its_settings() {
+    let mut calculator = calculator();
+    let scanner = calculator.compile().unwrap();
+    let seq = &sequences()[0];
+    let before = summary(&scanner.scan(seq));
+
+    // Later changes of the calculator only apply to newly compiled scanners
+    calculator.set_default_mss_threshold(NotNan::new(0.95).unwrap());
+    assert_eq!(summary(&scanner.scan(seq)), before);
+    assert!(calculator.compile().unwrap().scan(seq).len() < before.len());
+}
@@ -0,0 +1,63 @@
+use tfp::sequence::PlusStrand;
+use tfp::sink::TsvSink;
+
+mod common;
+
+use common::{calculator, summary};
+
+#[test]
+fn lazy_hits() {
+    let mut calculator = calculator();
+    calculator
+        .add_from_fasta_file("test_files/fasta.txt")
+        .unwrap();
+    // Small chunks give many batches of work units per sequence
+    calculator.set_chunk_length(10);
+    let scanner = calculator.compile().unwrap();
+    let expected = summary(&scanner.scan_many(&calculator.sequences));
+    assert!(expected.len() > 100);
+
+    let hits: Vec<_> = scanner.hits(&calculator.sequences).collect();
+    assert_eq!(summary(&hits), expected);
+    let first: Vec<_> = scanner.hits(&calculator.sequences).take(3).collect();
+    assert_eq!(summary(&first), expected[..3]);
+    assert_eq!(scanner.hits(Vec::<PlusStrand>::new()).count(), 0);
+
+    let mut sink = Vec::new();
+    calculator.clone().evaluate_into(&mut sink).unwrap();
+    assert_eq!(summary(&sink), summary(&calculator.evaluate().unwrap()));
+}
+
+#[test]
+fn write_tsv() {
+    let mut calculator = calculator();
+    let expected = calculator
+        .evaluate_fasta_file("test_files/fasta.txt")
+        .unwrap();
+    let mut sink = TsvSink::new(Vec::new());
+    calculator
+        .evaluate_fasta_file_into("test_files/fasta.txt", &mut sink)
+        .unwrap();
+    let tsv = String::from_utf8(sink.into_inner()).unwrap();
+    let mut lines = tsv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("sequence\tpos\tstrand\tmatrix\tfactors\tcss\tmss\tp_value\tlen\tsite\tmasked")
+    );
+    let lines: Vec<_> = lines.collect();
+    assert_eq!(lines.len(), expected.len());
+    let t = &expected[0];
+    let fields: Vec<_> = lines[0].split('\t').collect();
+    assert_eq!(fields[0], t.sequence);
+    assert_eq!(fields[1], t.pos.to_string());
+    assert_eq!(fields[3], t.matrix);
+    assert_eq!(fields[6].parse::<f32>().unwrap(), *t.mss);
+    assert_eq!(fields[9], t.site);
+
+    // Without hits only the header is written
+    let mut sink = TsvSink::new(Vec::new());
+    calculator
+        .evaluate_fasta_reader_into(">empty\n".as_bytes(), &mut sink)
+        .unwrap();
+    assert_eq!(sink.into_inner().iter().filter(|c| **c == b'\n').count(), 1);
+}
@@ -0,0 +1,143 @@
+use tfp::builder::Tfp;
+
+mod common;
+
+// Expected CSS/MSS values were computed by hand from the MATCH definitions:
+// counts * 100 + 1 normalised per row, I(i) = sum f ln(4f) and
+// score = (sum I(i) f(i, b) - min) / (max - min), core = best 5-mer by I(i) * max f(i)
+const EPSILON: f32 = 1e-4;
+
+fn evaluate(seq: &str) -> Vec<Tfp> {
+    common::calculator_for(seq).evaluate().unwrap()
+}
+
+fn find<'a>(hits: &'a [Tfp], matrix: &str, pos: usize, strand: bool) -> &'a Tfp {
+    hits.iter()
+        .find(|t| t.matrix == matrix && t.pos == pos && t.strand == strand)
+        .unwrap_or_else(|| panic!("No hit for {matrix} at {pos} ({strand})"))
+}
+
+fn assert_hit(hit: &Tfp, site: &str, css: f32, mss: f32) {
+    assert_eq!(hit.site, site);
+    assert!(
+        (*hit.css - css).abs() < EPSILON,
+        "css {} != {css} for {}",
+        hit.css,
+        hit.site
+    );
+    assert!(
+        (*hit.mss - mss).abs() < EPSILON,
+        "mss {} != {mss} for {}",
+        hit.mss,
+        hit.site
+    );
+}
+
+#[test]
+fn palindrome_hits_both_strands() {
+    let hits = evaluate("TTCACGTGTT");
+    assert_hit(find(&hits, "Test1", 2, true), "CACGTG", 1.0, 1.0);
+    assert_hit(find(&hits, "Test1", 2, false), "CACGTG", 1.0, 1.0);
+}
+
+#[test]
+fn minus_strand_is_reverse_complement() {
+    let hits = evaluate("GGAACGTGGG");
+    assert_hit(find(&hits, "Test1", 2, true), "AACGTG", 1.0, 0.928_081);
+    assert_hit(
+        find(&hits, "Test1", 2, false),
+        "CACGTT",
+        0.792_471,
+        0.812_371,
+    );
+
+    let hits = evaluate("GGCTCGTGGG");
+    assert_hit(
+        find(&hits, "Test1", 2, true),
+        "CTCGTG",
+        0.830_117,
+        0.846_408,
+    );
+    assert_hit(
+        find(&hits, "Test1", 2, false),
+        "CACGAG",
+        0.792_471,
+        0.812_371,
+    );
+}
+
+#[test]
+fn long_matrix_on_minus_strand() {
+    let hits = evaluate("CCGATTTGTTTATTACCC");
+    assert_hit(find(&hits, "Test2", 2, false), "GTAATAAACAAATC", 1.0, 1.0);
+    assert_hit(
+        find(&hits, "Test2", 2, true),
+        "GATTTGTTTATTAC",
+        0.375_769,
+        0.376_743,
+    );
+
+    let hits = evaluate("CCGATTTGTTTATTGCCC");
+    assert_hit(
+        find(&hits, "Test2", 2, false),
+        "GCAATAAACAAATC",
+        1.0,
+        0.967_035,
+    );
+    assert_hit(
+        find(&hits, "Test2", 2, true),
+        "GATTTGTTTATTGC",
+        0.375_769,
+        0.329_340,
+    );
+}
+
+#[test]
+fn every_window_is_scanned_once_per_strand() {
+    let seq = "CCGATTTGTTTATTACCCAGT";
+    let hits = evaluate(seq);
+    for (matrix, len) in [("Test1", 6), ("Test2", 14)] {
+        for strand in [true, false] {
+            let mut pos: Vec<_> = hits
+                .iter()
+                .filter(|t| t.matrix == matrix && t.strand == strand)
+                .map(|t| t.pos)
+                .collect();
+            pos.sort_unstable();
+            assert_eq!(pos, (0..=seq.len() - len).collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn sequence_shorter_than_matrix() {
+    let hits = evaluate("CACGTG");
+    assert!(hits.iter().all(|t| t.matrix == "Test1"));
+    assert_eq!(hits.len(), 2);
+}
+
+#[test]
+fn strands_mirror_on_reverse_complement_input() {
+    let seq = "CTAACACCACGGATTTTCCCCCTTTCATCCATGTGGGTGCCC";
+    let rc: String = seq
+        .chars()
+        .rev()
+        .map(|c| match c {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            _ => 'A',
+        })
+        .collect();
+
+    let forward = evaluate(seq);
+    let reverse = evaluate(&rc);
+    assert_eq!(forward.len(), reverse.len());
+    for hit in &forward {
+        let pos = seq.len() - hit.pos - hit.len;
+        let mirrored = find(&reverse, &hit.matrix, pos, !hit.strand);
+        assert_eq!(hit.site, mirrored.site);
+        assert_eq!(hit.css, mirrored.css);
+        assert_eq!(hit.mss, mirrored.mss);
+    }
+}
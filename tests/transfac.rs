@@ -0,0 +1,191 @@
+use tfp::builder::{PseudocountPolicy, Tfp, TfpCalculator};
+use tfp::matrix::{BindingFactor, Float, MatrixKind, MatrixMetadata, PwmMatrix};
+use tfp::parser::profile::Profile;
+use tfp::parser::transfac::{parse_matrices, write_transfac, TransfacParseError};
+use tfp::sequence::PlusStrand;
+
+fn parse(c: &str) -> Result<PwmMatrix, TransfacParseError> {
+    let (rest, mut matrices) = parse_matrices(c).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(matrices.len(), 1);
+    PwmMatrix::try_from(matrices.remove(0))
+}
+
+#[test]
+fn columns_follow_po() {
+    let acgt = parse("ID a\nPO A C G T\n01 1 2 3 4\n02 5 6 7 8\n//\n").unwrap();
+    let tgca = parse("ID a\nP0 T G C A\n01 4 3 2 1 N\n02 8 7 6 5 N\n//\n").unwrap();
+    assert_eq!(acgt.matrix, tgca.matrix);
+    assert_eq!(*acgt.matrix[(1, 2)], 7.0);
+}
+
+#[test]
+fn reject_malformed_matrices() {
+    assert!(matches!(
+        parse("ID a\n01 1 2 3 4\n//\n"),
+        Err(TransfacParseError::NoPo)
+    ));
+    assert!(matches!(
+        parse("ID a\nPO A C G U\n01 1 2 3 4\n//\n"),
+        Err(TransfacParseError::UnexpectedBase(b)) if b == "U"
+    ));
+    assert!(matches!(
+        parse("ID a\nPO A C G G\n01 1 2 3 4\n//\n"),
+        Err(TransfacParseError::IncompletePo(_))
+    ));
+    assert!(matches!(
+        parse("ID a\nPO A C G T\n01 1 2 3 4\n03 1 2 3 4\n//\n"),
+        Err(TransfacParseError::MissingRow(2))
+    ));
+    assert!(matches!(
+        parse("ID a\nPO A C G T\n01 1 2 3 4\n01 1 2 3 4\n//\n"),
+        Err(TransfacParseError::DuplicateRow(1))
+    ));
+    assert!(matches!(
+        parse("ID a\nPO A C G T\n01 1 2 3\n//\n"),
+        Err(TransfacParseError::WrongValueCount(1, 3))
+    ));
+}
+
+#[test]
+fn record_metadata() {
+    let m = parse(
+        "AC  M00001\nXX\nID  V$MYOD_01\nXX\nNA  MyoD\nXX\n\
+         DE  myoblast determination gene product\nDE  (E47 like)\nXX\n\
+         BF  T00526 MyoD; Species: mouse, Mus musculus.\n\
+         BF  T09177; MyoD; Species: human, Homo sapiens.\nXX\n\
+         PO  A C G T\n01 1 2 2 0 S\n02 2 1 2 0 R\nXX\n\
+         BA  5 functional elements in 3 genes\nXX\n\
+         CC  first line\nCC  second line\nXX\n//\n",
+    )
+    .unwrap();
+    let meta = &m.metadata;
+    assert_eq!(m.name, "V$MYOD_01");
+    assert_eq!(meta.accession.as_deref(), Some("M00001"));
+    assert_eq!(meta.factor.as_deref(), Some("MyoD"));
+    assert_eq!(
+        meta.description.as_deref(),
+        Some("myoblast determination gene product (E47 like)")
+    );
+    assert_eq!(
+        meta.basis.as_deref(),
+        Some("5 functional elements in 3 genes")
+    );
+    assert_eq!(meta.comment.as_deref(), Some("first line second line"));
+    assert_eq!(
+        meta.binding_factors,
+        vec![
+            BindingFactor {
+                id: "T00526".to_string(),
+                name: "MyoD".to_string(),
+                species: Some("mouse, Mus musculus".to_string()),
+            },
+            BindingFactor {
+                id: "T09177".to_string(),
+                name: "MyoD".to_string(),
+                species: Some("human, Homo sapiens".to_string()),
+            },
+        ]
+    );
+    assert!(meta.has_factor("myod"));
+    assert!(meta.has_species("Homo sapiens"));
+    assert!(!meta.has_species("rat"));
+    assert_eq!(meta.factor_names(), vec!["MyoD", "MyoD"]);
+}
+
+#[test]
+fn profile_by_accession() {
+    let mut c = TfpCalculator::default();
+    c.add_from_transfac_reader(
+        "AC  M00001\nID  V$TEST\nNA  Test\nPO A C G T\n\
+         01 0 0 20 0\n02 20 0 0 0\n03 0 0 0 20\n04 0 20 0 0\n05 20 0 0 0\n//\n"
+            .as_bytes(),
+    )
+    .unwrap();
+    c.add_profil(Profile {
+        cutoff: Default::default(),
+        css: Float::new(1.1).unwrap(),
+        mss: Float::new(0.0).unwrap(),
+        id: "M00001".to_string(),
+        factor: None,
+    });
+    c.add_sequence(PlusStrand::from_str("s", "GATCA").unwrap());
+    assert!(c.clone().evaluate().unwrap().is_empty());
+
+    c.profiles.clear();
+    let hits = c.evaluate().unwrap();
+    assert!(!hits.is_empty());
+    assert_eq!(hits[0].factors, vec!["Test"]);
+}
+
+#[test]
+fn write_and_parse_again() {
+    let parse_all = |c: &str| -> Vec<PwmMatrix> {
+        let (rest, matrices) = parse_matrices(c).unwrap();
+        assert!(rest.is_empty());
+        matrices
+            .into_iter()
+            .map(|m| PwmMatrix::try_from(m).unwrap())
+            .collect()
+    };
+    let mut matrices = parse_all(&std::fs::read_to_string("test_files/transfac.txt").unwrap());
+    matrices[0].metadata = MatrixMetadata {
+        factor: Some("Test".to_string()),
+        accession: Some("M00001".to_string()),
+        description: Some("multi\nline".to_string()),
+        binding_factors: vec![
+            BindingFactor {
+                id: "T00526".to_string(),
+                name: "MyoD".to_string(),
+                species: Some("mouse, Mus musculus".to_string()),
+            },
+            BindingFactor {
+                id: "T00001".to_string(),
+                name: String::new(),
+                species: None,
+            },
+        ],
+        basis: Some("basis".to_string()),
+        comment: Some("comment".to_string()),
+        log_odds_threshold: Some(6.5),
+        ..Default::default()
+    };
+    matrices[1].matrix[(0, 0)] = Float::new(0.1).unwrap();
+
+    let mut out = Vec::new();
+    write_transfac(&mut out, &matrices).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let written = parse_all(&out);
+    // Multi line fields are joined by spaces when parsed
+    matrices[0].metadata.description = Some("multi line".to_string());
+    assert_eq!(matrices, written);
+
+    // Writing is stable once the matrices went through the parser
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    write_transfac(&mut a, &matrices).unwrap();
+    write_transfac(&mut b, &written).unwrap();
+    assert_eq!(a, b);
+}
+
+#[test]
+fn frequencies_keep_their_metadata() {
+    let mut meme = TfpCalculator::default();
+    meme.add_from_meme_file("test_files/meme.txt").unwrap();
+    meme.set_pseudocount_policy(PseudocountPolicy::SqrtN);
+    meme.add_sequence(PlusStrand::from_str("test", "TTCACGTGAACAGCTGCCACGTATGCGTG").unwrap());
+    let mut out = Vec::new();
+    write_transfac(&mut out, &meme.matrices).unwrap();
+
+    let mut transfac = meme.clone();
+    transfac.matrices.clear();
+    transfac.add_from_transfac_reader(out.as_slice()).unwrap();
+    assert_eq!(transfac.matrices, meme.matrices);
+    assert_eq!(transfac.matrices[0].kind, MatrixKind::Frequencies);
+    assert_eq!(transfac.matrices[0].metadata.nsites, Some(20.0));
+
+    let key = |t: &Tfp| (t.matrix.clone(), t.pos, t.strand, t.css, t.mss);
+    let expected: Vec<_> = meme.evaluate().unwrap().iter().map(key).collect();
+    assert!(!expected.is_empty());
+    let found: Vec<_> = transfac.evaluate().unwrap().iter().map(key).collect();
+    assert_eq!(found, expected);
+}
@@ -1,6 +1,14 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use ::tfp::background::Background;
 use ::tfp::builder::{Tfp, TfpCalculator};
 use ::tfp::matrix::PwmMatrix;
+use ::tfp::parser::faidx::Region;
 use ::tfp::parser::fasta::Fasta;
+use ::tfp::scanner::Scanner;
+use ::tfp::sequence::PlusStrand;
+use ::tfp::sink::TsvSink;
 use parser::fasta::PyFasta;
 use parser::profile::{parse_profile, PyProfile};
 use parser::transfac::PyPwmMatrix;
@@ -21,6 +29,8 @@ pub struct PyTfp {
     #[pyo3(get, set)]
     matrix: String,
     #[pyo3(get, set)]
+    factors: Vec<String>,
+    #[pyo3(get, set)]
     pos: usize,
     #[pyo3(get, set)]
     strand: bool,
@@ -29,21 +39,31 @@ pub struct PyTfp {
     #[pyo3(get, set)]
     mss: f32,
     #[pyo3(get, set)]
+    p_value: f64,
+    #[pyo3(get, set)]
     len: usize,
+    #[pyo3(get, set)]
+    site: String,
+    #[pyo3(get, set)]
+    masked: bool,
 }
 
 #[pymethods]
 impl PyTfp {
     fn __repr__(&self) -> String {
         format!(
-            "Tfp (sequence: {}, matrix: {}, pos: {}, strand: {}, css: {}, mss: {}, len: {})",
+            "Tfp (sequence: {}, matrix: {}, factors: {:?}, pos: {}, strand: {}, css: {}, mss: {}, p_value: {}, len: {}, site: {}, masked: {})",
             self.sequence,
             self.matrix,
+            self.factors,
             self.pos,
             if self.strand { "+" } else { "-" },
             self.css,
             self.mss,
-            self.len
+            self.p_value,
+            self.len,
+            self.site,
+            self.masked
         )
     }
 }
@@ -53,15 +73,62 @@ impl From<Tfp> for PyTfp {
         PyTfp {
             sequence: t.sequence,
             matrix: t.matrix,
+            factors: t.factors,
             pos: t.pos,
             strand: t.strand,
             css: *t.css,
             mss: *t.mss,
+            p_value: t.p_value,
             len: t.len,
+            site: t.site,
+            masked: t.masked,
         }
     }
 }
 
+fn to_plus_strand(fasta: PyFasta) -> PyResult<PlusStrand> {
+    Fasta::from(fasta)
+        .try_into()
+        .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+}
+
+/// Compiled matrices, see [`Scanner`]
+#[pyclass(name = "Scanner")]
+#[derive(Debug)]
+pub struct PyScanner {
+    scanner: Scanner,
+}
+
+#[pymethods]
+impl PyScanner {
+    fn __repr__(&self) -> String {
+        format!("Scanner (matrices: {:?})", self.scanner.matrix_names())
+    }
+
+    fn scan(&self, fasta: PyFasta) -> PyResult<Vec<PyTfp>> {
+        let seq = to_plus_strand(fasta)?;
+        Ok(self
+            .scanner
+            .scan(&seq)
+            .into_iter()
+            .map(PyTfp::from)
+            .collect())
+    }
+
+    fn scan_many(&self, fastas: Vec<PyFasta>) -> PyResult<Vec<PyTfp>> {
+        let seqs = fastas
+            .into_iter()
+            .map(to_plus_strand)
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(self
+            .scanner
+            .scan_many(&seqs)
+            .par_drain(..)
+            .map(PyTfp::from)
+            .collect())
+    }
+}
+
 #[pyclass(name = "TfpCalculator")]
 #[derive(Debug, Default)]
 pub struct PyTfpCalculator {
@@ -103,7 +170,7 @@ impl PyTfpCalculator {
             .map(|(id, (css, mss))| format!("Profile(id: {id}, css: {css}, mss: {mss})\n"))
             .collect::<String>();
 
-        format!("TfpCalculator: \n{matrices} {sequences} {profiles} default_css_threshold: {}, default_mss_threshold: {}", self.calculator.default_css_threshold, self.calculator.default_mss_threshold)
+        format!("TfpCalculator: \n{matrices} {sequences} {profiles} default_css_threshold: {}, default_mss_threshold: {}, ambiguity_policy: {}, mask_policy: {}", self.calculator.default_css_threshold, self.calculator.default_mss_threshold, self.calculator.ambiguity_policy, self.calculator.mask_policy)
     }
 
     #[setter]
@@ -136,6 +203,115 @@ impl PyTfpCalculator {
         *self.calculator.get_default_mss_threshold()
     }
 
+    #[setter]
+    fn set_ambiguity_policy(&mut self, value: &str) -> PyResult<()> {
+        self.calculator.set_ambiguity_policy(
+            value
+                .parse()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
+        );
+        Ok(())
+    }
+
+    #[getter]
+    fn get_ambiguity_policy(&self) -> String {
+        self.calculator.get_ambiguity_policy().to_string()
+    }
+
+    #[setter]
+    fn set_mask_policy(&mut self, value: &str) -> PyResult<()> {
+        self.calculator.set_mask_policy(
+            value
+                .parse()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
+        );
+        Ok(())
+    }
+
+    #[getter]
+    fn get_mask_policy(&self) -> String {
+        self.calculator.get_mask_policy().to_string()
+    }
+
+    #[setter]
+    fn set_p_value_threshold(&mut self, value: Option<f64>) {
+        self.calculator.set_p_value_threshold(value);
+    }
+
+    #[getter]
+    fn get_p_value_threshold(&self) -> Option<f64> {
+        self.calculator.get_p_value_threshold()
+    }
+
+    #[setter]
+    fn set_pseudocount_policy(&mut self, value: &str) -> PyResult<()> {
+        self.calculator.set_pseudocount_policy(
+            value
+                .parse()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
+        );
+        Ok(())
+    }
+
+    #[getter]
+    fn get_pseudocount_policy(&self) -> String {
+        self.calculator.get_pseudocount_policy().to_string()
+    }
+
+    #[setter]
+    fn set_pseudocount(&mut self, value: f32) -> PyResult<()> {
+        self.calculator.set_pseudocount(
+            value
+                .try_into()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
+        );
+        Ok(())
+    }
+
+    #[getter]
+    fn get_pseudocount(&self) -> f32 {
+        *self.calculator.get_pseudocount()
+    }
+
+    #[setter]
+    fn set_core_length(&mut self, value: usize) {
+        self.calculator.set_core_length(value);
+    }
+
+    #[getter]
+    fn get_core_length(&self) -> usize {
+        self.calculator.get_core_length()
+    }
+
+    #[setter]
+    fn set_lenient(&mut self, value: bool) {
+        self.calculator.set_lenient(value);
+    }
+
+    #[getter]
+    fn get_lenient(&self) -> bool {
+        self.calculator.get_lenient()
+    }
+
+    #[setter]
+    fn set_meme_background(&mut self, value: bool) {
+        self.calculator.set_meme_background(value);
+    }
+
+    #[getter]
+    fn get_meme_background(&self) -> bool {
+        self.calculator.get_meme_background()
+    }
+
+    #[getter]
+    fn get_warnings(&self) -> Vec<String> {
+        self.calculator
+            .warnings
+            .iter()
+            .map(|w| w.to_string())
+            .collect()
+    }
+
     fn add_from_profile_file(&mut self, path: &str) -> PyResult<()> {
         self.calculator
             .add_from_profile_file(path)
@@ -148,23 +324,99 @@ impl PyTfpCalculator {
             .map_err(|e| PyOSError::new_err(format!("{e:?}")))
     }
 
+    fn add_from_meme_file(&mut self, path: &str) -> PyResult<()> {
+        self.calculator
+            .add_from_meme_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn add_from_homer_file(&mut self, path: &str) -> PyResult<()> {
+        self.calculator
+            .add_from_homer_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn set_background_from_meme_file(&mut self, path: &str) -> PyResult<()> {
+        self.calculator
+            .set_background_from_meme_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn estimate_background(&mut self, order: usize) {
+        self.calculator.estimate_background(order);
+    }
+
+    /// Base composition of the background in A, C, G, T order
+    #[getter]
+    fn get_background(&self) -> [f64; 4] {
+        self.calculator.get_background().frequencies()
+    }
+
+    #[setter]
+    fn set_background(&mut self, value: [f64; 4]) -> PyResult<()> {
+        let background = Background::from_frequencies(value)
+            .ok_or_else(|| PyOSError::new_err("Frequencies have to be positive"))?;
+        self.calculator.set_background(background);
+        Ok(())
+    }
+
+    fn add_from_jaspar_file(&mut self, path: &str) -> PyResult<()> {
+        self.calculator
+            .add_from_jaspar_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn add_from_pfm_file(&mut self, path: &str) -> PyResult<()> {
+        self.calculator
+            .add_from_pfm_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
     fn add_from_fasta_file(&mut self, path: &str) -> PyResult<()> {
         self.calculator
             .add_from_fasta_file(path)
             .map_err(|e| PyOSError::new_err(format!("{e:?}")))
     }
 
+    fn add_regions_from_fasta_file(&mut self, path: &str, regions: Vec<String>) -> PyResult<()> {
+        let regions = regions
+            .iter()
+            .map(|r| r.parse::<Region>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))?;
+        self.calculator
+            .add_regions_from_fasta_file(path, &regions)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn add_from_bed_file(&mut self, bed: &str, fasta: &str) -> PyResult<()> {
+        self.calculator
+            .add_from_bed_file(bed, fasta)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    fn use_profile_set(&mut self, name: &str) -> PyResult<()> {
+        self.calculator
+            .use_profile_set(name)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
+    }
+
+    #[getter]
+    fn get_profile_sets(&self) -> Vec<String> {
+        self.calculator
+            .profile_sets
+            .iter()
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
     fn add_profile(&mut self, profile: PyProfile) -> PyResult<()> {
         self.calculator.add_profil(profile.try_into()?);
         Ok(())
     }
 
     fn add_fasta(&mut self, fasta: PyFasta) -> PyResult<()> {
-        self.calculator.add_sequence(
-            Fasta::from(fasta)
-                .try_into()
-                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
-        );
+        self.calculator.add_sequence(to_plus_strand(fasta)?);
         Ok(())
     }
 
@@ -172,19 +424,48 @@ impl PyTfpCalculator {
         self.calculator.add_pwm(PwmMatrix::from(pwm));
     }
 
-    fn evaluate(&self) -> Vec<PyTfp> {
-        self.calculator
-            .clone()
-            .evaluate()
+    fn compile(&self) -> PyResult<PyScanner> {
+        Ok(PyScanner {
+            scanner: self
+                .calculator
+                .compile()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
+        })
+    }
+
+    fn evaluate(&self) -> PyResult<Vec<PyTfp>> {
+        Ok(self
+            .compile()?
+            .scanner
+            .scan_many(&self.calculator.sequences)
             .par_drain(..)
             .map(PyTfp::from)
-            .collect()
+            .collect())
+    }
+
+    fn evaluate_fasta_file(&mut self, path: &str) -> PyResult<Vec<PyTfp>> {
+        Ok(self
+            .calculator
+            .evaluate_fasta_file(path)
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))?
+            .par_drain(..)
+            .map(PyTfp::from)
+            .collect())
+    }
+
+    /// Writes the hits of every record as tab separated lines to `output` while scanning
+    fn write_fasta_file(&mut self, path: &str, output: &str) -> PyResult<()> {
+        let file = File::create(output).map_err(|e| PyOSError::new_err(format!("{e:?}")))?;
+        self.calculator
+            .evaluate_fasta_file_into(path, &mut TsvSink::new(BufWriter::new(file)))
+            .map_err(|e| PyOSError::new_err(format!("{e:?}")))
     }
 }
 
 #[pymodule]
 fn tfp(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyTfpCalculator>()?;
+    m.add_class::<PyScanner>()?;
 
     m.add_function(wrap_pyfunction!(parse_profile, m)?)?;
     m.add_class::<PyProfile>()?;
@@ -1,6 +1,6 @@
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
-use tfp::parser::fasta::Fasta;
+use tfp::parser::fasta::{Fasta, FastaReader};
 
 #[pyclass(name = "Fasta")]
 #[derive(Debug, Clone)]
@@ -38,13 +38,8 @@ impl From<Fasta> for PyFasta {
 
 #[pyfunction]
 pub fn parse_fasta(c: &str) -> PyResult<Vec<PyFasta>> {
-    let (rest, mut fasta) =
-        Fasta::parse_many(c).map_err(|e| PyOSError::new_err(format!("{e:?}")))?;
-    if !rest.is_empty() {
-        return Err(PyOSError::new_err(format!(
-            "Could not parse completly. {rest}"
-        )));
-    }
-
-    Ok(fasta.drain(..).map(PyFasta::from).collect())
+    FastaReader::new(c.as_bytes())
+        .map(|f| f.map(PyFasta::from))
+        .collect::<Result<_, _>>()
+        .map_err(|e| PyOSError::new_err(format!("{e:?}")))
 }
@@ -1,24 +1,28 @@
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
-use tfp::parser::profile::Profile;
+use tfp::parser::profile::{Profile, ProfileSet};
 
 #[pyclass(name = "Profile")]
 #[derive(Debug, Clone)]
 pub struct PyProfile {
+    #[pyo3(get, set)]
+    pub cutoff: u8,
     #[pyo3(get, set)]
     pub css: f32,
     #[pyo3(get, set)]
     pub mss: f32,
     #[pyo3(get, set)]
     pub id: String,
+    #[pyo3(get, set)]
+    pub factor: Option<String>,
 }
 
 #[pymethods]
 impl PyProfile {
     fn __repr__(&self) -> String {
         format!(
-            "Profile(id: {}, css: {}, mss: {})",
-            self.id, self.css, self.mss
+            "Profile(id: {}, css: {}, mss: {}, cutoff: {}, factor: {:?})",
+            self.id, self.css, self.mss, self.cutoff, self.factor
         )
     }
 }
@@ -28,6 +32,11 @@ impl TryFrom<PyProfile> for Profile {
 
     fn try_from(value: PyProfile) -> Result<Self, Self::Error> {
         Ok(Self {
+            cutoff: value
+                .cutoff
+                .to_string()
+                .parse()
+                .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
             css: value
                 .css
                 .try_into()
@@ -37,25 +46,39 @@ impl TryFrom<PyProfile> for Profile {
                 .try_into()
                 .map_err(|e| PyOSError::new_err(format!("{e:?}")))?,
             id: value.id,
+            factor: value.factor,
         })
     }
 }
 
+impl From<Profile> for PyProfile {
+    fn from(p: Profile) -> Self {
+        Self {
+            cutoff: p
+                .cutoff
+                .to_string()
+                .parse()
+                .expect("Cutoff types are numbers"),
+            css: *p.css,
+            mss: *p.mss,
+            id: p.id,
+            factor: p.factor,
+        }
+    }
+}
+
 #[pyfunction]
 pub fn parse_profile(c: &str) -> PyResult<Vec<PyProfile>> {
-    let (rest, mut profiles) =
-        Profile::parse_many(c).map_err(|e| PyOSError::new_err(format!("{e:?}")))?;
+    let (rest, sets) =
+        ProfileSet::parse_many(c).map_err(|e| PyOSError::new_err(format!("{e:?}")))?;
     if !rest.is_empty() {
         return Err(PyOSError::new_err(format!(
             "Could not parse completly. {rest}"
         )));
     }
-    Ok(profiles
-        .drain()
-        .map(|(id, (css, mss))| PyProfile {
-            id,
-            css: *css,
-            mss: *mss,
-        })
+    Ok(sets
+        .into_iter()
+        .flat_map(|s| s.profiles)
+        .map(PyProfile::from)
         .collect())
 }
@@ -1,7 +1,7 @@
 use nalgebra::DVector;
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
-use tfp::matrix::{Float, PwmMatrix, PwmMatrixInner};
+use tfp::matrix::{Float, MatrixKind, MatrixMetadata, PwmMatrix, PwmMatrixInner};
 use tfp::parser::transfac::parse_matrices;
 
 #[pyclass(name = "PwmMatrix")]
@@ -10,6 +10,8 @@ pub struct PyPwmMatrix {
     #[pyo3(get, set)]
     pub name: String,
     pub matrix: PwmMatrixInner,
+    pub kind: MatrixKind,
+    pub metadata: MatrixMetadata,
 }
 
 #[pymethods]
@@ -42,7 +44,12 @@ impl PyPwmMatrix {
         );
         matrix.set_column(3, &column_3);
 
-        Ok(Self { name, matrix })
+        Ok(Self {
+            name,
+            kind: MatrixKind::detect(&matrix),
+            matrix,
+            metadata: Default::default(),
+        })
     }
 
     #[getter]
@@ -60,6 +67,40 @@ impl PyPwmMatrix {
             .collect::<Vec<_>>()
     }
 
+    /// "counts" or "frequencies"
+    #[getter]
+    fn get_kind(&self) -> String {
+        self.kind.to_string()
+    }
+
+    #[getter]
+    fn get_factor(&self) -> Option<String> {
+        self.metadata.factor.clone()
+    }
+
+    #[getter]
+    fn get_accession(&self) -> Option<String> {
+        self.metadata.accession.clone()
+    }
+
+    #[getter]
+    fn get_description(&self) -> Option<String> {
+        self.metadata.description.clone()
+    }
+
+    #[getter]
+    fn get_factors(&self) -> Vec<String> {
+        self.metadata.factor_names()
+    }
+
+    fn has_factor(&self, name: &str) -> bool {
+        self.metadata.has_factor(name)
+    }
+
+    fn has_species(&self, species: &str) -> bool {
+        self.metadata.has_species(species)
+    }
+
     pub fn __repr__(&self) -> String {
         format!("PwmMatrix: {} {}", self.name, self.matrix)
     }
@@ -70,6 +111,8 @@ impl From<PyPwmMatrix> for PwmMatrix {
         Self {
             name: m.name,
             matrix: m.matrix,
+            kind: m.kind,
+            metadata: m.metadata,
         }
     }
 }
@@ -79,6 +122,8 @@ impl From<PwmMatrix> for PyPwmMatrix {
         Self {
             name: m.name,
             matrix: m.matrix,
+            kind: m.kind,
+            metadata: m.metadata,
         }
     }
 }